@@ -1,18 +1,20 @@
-mod math;
-mod sprite_batch;
-mod application;
-mod sprite;
-mod color;
-
-use std::{collections::HashMap, default::Default};
-use application::ApplicationContext;
-use color::Color;
-use glium::{Blend, uniforms::MagnifySamplerFilter};
-use image::ImageFormat;
-use math::{Rectangle, Vector2};
 use rand::{Rng, rngs::ThreadRng};
-use sprite::{Sprite, SpriteLoader};
-use sprite_batch::{SpriteBatch, DrawData};
+use sprite_batching::{
+    Animation, 
+    AnimationPlayer, 
+    ApplicationContext, 
+    Color, 
+    DrawData, 
+    Grid, 
+    LoopMode, 
+    Renderer, 
+    SpriteBatch, 
+    SpriteBatchError, 
+    SpriteLoader, 
+    SpriteSortMode, 
+    glium::{Blend, uniforms::MagnifySamplerFilter},
+    math::Vector2
+};
 
 struct Particle {
     position: Vector2,
@@ -22,7 +24,7 @@ struct Particle {
 }
 
 struct Application { 
-    sprites: HashMap<String, Sprite>,
+    slime: AnimationPlayer,
     particles: Vec<Particle>,
     time: f32,
     next_spawn_time: f32,
@@ -32,22 +34,17 @@ struct Application {
 
 impl ApplicationContext for Application {
     fn new() -> Self {
-        Self { sprites: HashMap::new(), particles: Vec::new(), time: 0f32, next_spawn_time: 0f32, random: rand::thread_rng(), background_color: Color::GREEN }
+        Self { slime: AnimationPlayer::new(), particles: Vec::new(), time: 0f32, next_spawn_time: 0f32, random: rand::thread_rng(), background_color: Color::GREEN }
     }
 
     fn load(&mut self, sprite_loader: &mut SpriteLoader) {
-        self.sprites.insert(
-            "Slime".to_owned(), 
-            sprite_loader.load_sprite(
-                image::load_from_memory_with_format(
-                    include_bytes!("../assets/Slime.png"), 
-                    ImageFormat::Png
-                ).unwrap().into_rgba8()
-            )
-        );
+        let slime = sprite_loader.load_sprite_sheet_file(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/Slime.png"), &Grid::new((16, 16))).unwrap();
+        // The idle frames are stacked from the bottom of the strip up.
+        self.slime.add_clip("Idle", Animation::from_regions(slime.regions().iter().rev().copied(), 0.25, LoopMode::Loop));
+        self.slime.play("Idle");
     }
 
-    fn update(&mut self, delta_time: f32) {
+    fn update(&mut self, delta_time: f32, _sprite_loader: &mut SpriteLoader) {
         println!("FPS: {}", 1f32 / delta_time);
         if self.time > self.next_spawn_time {
             self.next_spawn_time = self.time + 0.0001f32;
@@ -72,30 +69,33 @@ impl ApplicationContext for Application {
             }
         );
 
+        self.slime.update(delta_time);
         self.time += delta_time;
     }
 
-    fn draw(&self, sprite_batch: &mut SpriteBatch) { 
-        sprite_batch.clear_color(self.background_color);
-
+    fn draw(&self, sprite_batch: &mut SpriteBatch, renderer: &mut dyn Renderer) -> Result<(), SpriteBatchError> { 
         sprite_batch.sampler_behaviour.magnify_filter = MagnifySamplerFilter::Nearest;
         sprite_batch.draw_parameters.blend = Blend::alpha_blending();
+
+        sprite_batch.begin(SpriteSortMode::Deferred);
+        sprite_batch.clear_color(self.background_color);
         for particle in self.particles.iter() {
-            sprite_batch.draw(
-                DrawData {
-                    position: particle.position,
-                    sprite: self.sprites["Slime"],
-                    source: Some(Rectangle::new(0f32, ((self.time * 4f32) as u32 % 3) as f32 * 16f32, 16f32, 16f32)),
-                    scale: Vector2::ONE * 10f32,
-                    origin: Vector2::ONE * 8f32,
-                    rotation: particle.rotation,
-                    ..Default::default()
-                }
-            );
+            let draw_data = DrawData {
+                position: particle.position,
+                scale: Vector2::ONE * 10f32,
+                origin: Vector2::ONE * 8f32,
+                rotation: particle.rotation,
+                ..Default::default()
+            };
+            for draw_data in self.slime.draw_data(&draw_data) {
+                sprite_batch.draw(draw_data);
+            }
         }
+
+        sprite_batch.end(renderer)
     }
 }
 
 fn main() {
-    application::run::<Application>();
+    sprite_batching::run::<Application>();
 }
@@ -0,0 +1,371 @@
+use std::collections::HashMap;
+
+use defaults::Defaults;
+
+use crate::{
+    color::Color,
+    math::Vector2,
+    sprite_batch::DrawData,
+    sprite_sheet::TextureRegion,
+    texture_atlas::{TagDirection, TextureAtlas}
+};
+
+/// The order an animation shows its frames in, and what happens after the last one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LoopMode {
+    /// Starts over from the first frame.
+    #[default]
+    Loop,
+    /// Stops on the last frame.
+    Once,
+    /// Plays forward then backward, without showing the first and last frames twice in a row.
+    PingPong,
+    /// Plays from the last frame to the first, then starts over.
+    Reverse
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationFrame {
+    pub region: TextureRegion,
+    /// Replaces the origin the frame is drawn with, such as the pivot of an atlas frame. `None` keeps the origin of the sprite.
+    pub origin: Option<Vector2>,
+    /// Added to the rotation the frame is drawn with, to turn frames packed rotated upright.
+    pub rotation: f32,
+    /// How long the frame is shown, in seconds.
+    pub duration: f32,
+    /// The name of the event fired when the frame is shown.
+    pub event: Option<String>
+}
+
+impl AnimationFrame {
+    pub fn new(region: TextureRegion, duration: f32) -> Self {
+        Self { region, origin: None, rotation: 0f32, duration, event: None }
+    }
+}
+
+/// A clip of frames shown one after another.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Animation {
+    frames: Vec<AnimationFrame>,
+    pub mode: LoopMode
+}
+
+impl Animation {
+    /// # Panics
+    /// Panics if there are no frames or a frame lasts no time, since such an animation could never advance.
+    pub fn new(frames: Vec<AnimationFrame>, mode: LoopMode) -> Self {
+        assert!(!frames.is_empty(), "an animation needs at least one frame");
+        assert!(frames.iter().all(|frame| frame.duration > 0f32), "animation frames must last longer than zero seconds");
+        Self { frames, mode }
+    }
+
+    /// An animation showing every region for `frame_duration` seconds, such as the cells of a `SpriteSheet`.
+    pub fn from_regions(regions: impl IntoIterator<Item = TextureRegion>, frame_duration: f32, mode: LoopMode) -> Self {
+        Self::new(regions.into_iter().map(|region| AnimationFrame::new(region, frame_duration)).collect(), mode)
+    }
+
+    /// The frames of the atlas tag called `name`, drawn with their pivots as the origin, or `None` if there is no such tag.
+    /// The direction of the tag becomes the mode, and forward or reverse tags played once become `LoopMode::Once`.
+    /// Other repeat counts are not kept, since a mode either stops after one pass or loops forever: tags repeated more
+    /// than once, and ping-pong tags however often they repeat, loop. Frames without a duration, or with one of zero
+    /// or less, are shown for a tenth of a second, like in Aseprite.
+    pub fn from_atlas_tag(atlas: &TextureAtlas, name: &str) -> Option<Self> {
+        let tag = atlas.tag(name)?;
+        let mut frames: Vec<AnimationFrame> = atlas.tag_frames(name)?.iter().map(
+            |frame| {
+                let draw_data = frame.draw_data();
+                AnimationFrame {
+                    region: frame.region,
+                    origin: Some(draw_data.origin),
+                    rotation: draw_data.rotation,
+                    duration: frame.duration.filter(|duration| *duration > 0f32).unwrap_or(0.1),
+                    event: None
+                }
+            }
+        ).collect();
+
+        let mode = match (tag.direction, tag.repeat) {
+            (TagDirection::Forward | TagDirection::Reverse, Some(1)) => LoopMode::Once,
+            (TagDirection::Forward, _) => LoopMode::Loop,
+            (TagDirection::Reverse, _) => LoopMode::Reverse,
+            (TagDirection::PingPong | TagDirection::PingPongReverse, _) => LoopMode::PingPong
+        };
+        if matches!((tag.direction, mode), (TagDirection::Reverse, LoopMode::Once) | (TagDirection::PingPongReverse, _)) {
+            frames.reverse();
+        }
+
+        Some(Self::new(frames, mode))
+    }
+
+    /// Fires the event called `name` whenever the frame at `frame` is shown.
+    ///
+    /// # Panics
+    /// Panics if there is no frame at `frame`.
+    pub fn with_event(mut self, frame: usize, name: impl Into<String>) -> Self {
+        self.frames[frame].event = Some(name.into());
+        self
+    }
+
+    pub fn frames(&self) -> &[AnimationFrame] {
+        &self.frames
+    }
+
+    /// How long it takes to show every frame once in the order of the mode, in seconds.
+    pub fn duration(&self) -> f32 {
+        (0..self.steps()).map(|step| self.frames[self.frame_index(step)].duration).sum()
+    }
+
+    /// How many frames one pass through the animation shows.
+    fn steps(&self) -> usize {
+        match self.mode {
+            LoopMode::PingPong if self.frames.len() > 2 => 2 * self.frames.len() - 2,
+            _ => self.frames.len()
+        }
+    }
+
+    /// The index of the frame shown at `step` of a pass.
+    fn frame_index(&self, step: usize) -> usize {
+        let last = self.frames.len() - 1;
+        match self.mode {
+            LoopMode::Loop | LoopMode::Once => step,
+            LoopMode::PingPong if step > last => 2 * last - step,
+            LoopMode::PingPong => step,
+            LoopMode::Reverse => last - step
+        }
+    }
+}
+
+/// Fired by `AnimationPlayer::update` when a frame with an event is shown.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AnimationEvent {
+    pub name: String,
+    pub clip: String,
+    /// The index of the frame in its animation.
+    pub frame: usize
+}
+
+#[derive(Clone, Debug)]
+struct Playback {
+    clip: String,
+    step: usize,
+    /// How long the current frame has been shown.
+    time: f32,
+    finished: bool
+}
+
+impl Playback {
+    fn new(clip: &str) -> Self {
+        Self { clip: clip.to_owned(), step: 0, time: 0f32, finished: false }
+    }
+
+    /// Moves `delta_time` seconds forward, calling `show` with the index of every frame that starts being shown.
+    /// Whole passes through a repeating animation end where they started, so they are skipped without showing their frames.
+    fn advance(&mut self, animation: &Animation, mut delta_time: f32, mut show: impl FnMut(usize)) {
+        let duration = animation.duration();
+        if animation.mode != LoopMode::Once && delta_time >= duration {
+            delta_time %= duration;
+        }
+
+        while !self.finished {
+            let remaining = animation.frames[animation.frame_index(self.step)].duration - self.time;
+            if delta_time < remaining {
+                self.time += delta_time;
+                return;
+            }
+
+            delta_time -= remaining;
+            if animation.mode == LoopMode::Once && self.step + 1 == animation.steps() {
+                self.time += remaining;
+                self.finished = true;
+                return;
+            }
+
+            self.step = (self.step + 1) % animation.steps();
+            self.time = 0f32;
+            show(animation.frame_index(self.step));
+        }
+    }
+}
+
+type EventCallback = Box<dyn FnMut(&AnimationEvent)>;
+
+struct Crossfade {
+    from: Playback,
+    elapsed: f32,
+    duration: f32
+}
+
+/// Plays named animation clips, one at a time or crossfading from one to another.
+/// It only moves forward in `update`, so the same sequence of delta times always shows the same frames and fires
+/// the same events.
+#[derive(Defaults)]
+pub struct AnimationPlayer {
+    clips: HashMap<String, Animation>,
+    current: Option<Playback>,
+    crossfade: Option<Crossfade>,
+    /// Whether the events of the first frame of the current clip have been fired.
+    started: bool,
+    callbacks: Vec<(String, EventCallback)>,
+    /// Multiplies the delta time of `update`. Negative speeds are treated as zero; use `LoopMode::Reverse` to play backward.
+    #[def = "1f32"]
+    pub speed: f32
+}
+
+impl AnimationPlayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a clip to play by `name`, replacing the clip of the same name, which restarts if it is playing.
+    pub fn add_clip(&mut self, name: impl Into<String>, animation: Animation) {
+        let name = name.into();
+        if self.current_clip() == Some(name.as_str()) {
+            self.started = false;
+        }
+        let crossfading = self.crossfade.iter_mut().map(|crossfade| &mut crossfade.from);
+        for playback in self.current.iter_mut().chain(crossfading).filter(|playback| playback.clip == name) {
+            *playback = Playback::new(&name);
+        }
+        self.clips.insert(name, animation);
+    }
+
+    pub fn clip(&self, name: &str) -> Option<&Animation> {
+        self.clips.get(name)
+    }
+
+    /// Calls `callback` whenever a frame with the event called `name` is shown.
+    pub fn on_event(&mut self, name: impl Into<String>, callback: impl FnMut(&AnimationEvent) + 'static) {
+        self.callbacks.push((name.into(), Box::new(callback)));
+    }
+
+    /// Starts the clip called `name` from its first frame, stopping any crossfade.
+    /// Does nothing if the clip is already playing and has not finished, so it can be called every frame.
+    ///
+    /// # Panics
+    /// Panics if there is no clip called `name`.
+    pub fn play(&mut self, name: &str) {
+        assert!(self.clips.contains_key(name), "there is no animation clip called `{name}`");
+        if self.is_playing(name) {
+            return;
+        }
+
+        self.current = Some(Playback::new(name));
+        self.crossfade = None;
+        self.started = false;
+    }
+
+    /// Starts the clip called `name` from its first frame and fades it in over `duration` seconds, drawn on top of
+    /// the current clip, which keeps playing until it is faded out. Does nothing if the clip is already playing and has not finished.
+    ///
+    /// # Panics
+    /// Panics if there is no clip called `name`.
+    pub fn crossfade(&mut self, name: &str, duration: f32) {
+        if self.is_playing(name) {
+            return;
+        }
+
+        let from = self.current.take();
+        self.play(name);
+        if let Some(from) = from.filter(|_| duration > 0f32) {
+            self.crossfade = Some(Crossfade { from, elapsed: 0f32, duration });
+        }
+    }
+
+    /// Shows the frames of the next `delta_time` seconds, times `speed`, firing the events of every frame that starts being shown.
+    /// When that is longer than a whole pass through a repeating clip, the whole passes are skipped and only the events
+    /// of the frames shown during the remaining time are fired.
+    ///
+    /// # Panics
+    /// Panics if `delta_time` times `speed` is not finite.
+    pub fn update(&mut self, delta_time: f32) {
+        let delta_time = delta_time * self.speed.max(0f32);
+        assert!(delta_time.is_finite(), "animations cannot advance by {delta_time} seconds");
+        let Some(current) = &mut self.current else {
+            return;
+        };
+
+        let animation = &self.clips[&current.clip];
+        let mut shown = Vec::new();
+        if !self.started {
+            shown.push(animation.frame_index(0));
+            self.started = true;
+        }
+        current.advance(animation, delta_time, |frame| shown.push(frame));
+
+        let events: Vec<AnimationEvent> = shown.into_iter().filter_map(
+            |frame| animation.frames[frame].event.clone().map(|name| AnimationEvent { name, clip: current.clip.clone(), frame })
+        ).collect();
+
+        if let Some(crossfade) = &mut self.crossfade {
+            crossfade.from.advance(&self.clips[&crossfade.from.clip], delta_time, |_| ());
+            crossfade.elapsed += delta_time;
+            if crossfade.elapsed >= crossfade.duration {
+                self.crossfade = None;
+            }
+        }
+
+        for event in &events {
+            for (name, callback) in &mut self.callbacks {
+                if *name == event.name {
+                    callback(event);
+                }
+            }
+        }
+    }
+
+    /// The name of the clip being played.
+    pub fn current_clip(&self) -> Option<&str> {
+        self.current.as_ref().map(|current| current.clip.as_str())
+    }
+
+    /// The index of the frame shown, in its animation.
+    pub fn frame_index(&self) -> Option<usize> {
+        self.current.as_ref().map(|current| self.clips[&current.clip].frame_index(current.step))
+    }
+
+    pub fn current_frame(&self) -> Option<&AnimationFrame> {
+        self.current.as_ref().map(|current| self.frame_of(current))
+    }
+
+    /// Whether the current clip is `LoopMode::Once` and has shown its last frame for its whole duration.
+    pub fn is_finished(&self) -> bool {
+        self.current.as_ref().is_some_and(|current| current.finished)
+    }
+
+    /// How far the current clip has faded in, from 0 to 1.
+    pub fn crossfade_weight(&self) -> f32 {
+        self.crossfade.as_ref().map_or(1f32, |crossfade| (crossfade.elapsed / crossfade.duration).min(1f32))
+    }
+
+    /// `draw_data` showing the current frame instead of its sprite. While crossfading, the frame of the previous clip
+    /// comes first, and the current frame is made more transparent by the crossfade weight to be drawn over it.
+    pub fn draw_data(&self, draw_data: &DrawData) -> Vec<DrawData> {
+        let mut frames = Vec::with_capacity(2);
+        if let Some(crossfade) = &self.crossfade {
+            frames.push((self.frame_of(&crossfade.from), 1f32));
+        }
+        if let Some(current) = &self.current {
+            frames.push((self.frame_of(current), self.crossfade_weight()));
+        }
+
+        frames.into_iter().map(
+            |(frame, weight)| DrawData {
+                sprite: frame.region.sprite,
+                source: Some(frame.region.source),
+                origin: frame.origin.unwrap_or(draw_data.origin),
+                rotation: draw_data.rotation + frame.rotation,
+                color: Color { alpha: draw_data.color.alpha * weight, ..draw_data.color },
+                ..draw_data.clone()
+            }
+        ).collect()
+    }
+
+    fn is_playing(&self, name: &str) -> bool {
+        self.current.as_ref().is_some_and(|current| current.clip == name && !current.finished)
+    }
+
+    fn frame_of(&self, playback: &Playback) -> &AnimationFrame {
+        let animation = &self.clips[&playback.clip];
+        &animation.frames[animation.frame_index(playback.step)]
+    }
+}
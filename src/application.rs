@@ -1,62 +1,180 @@
-use std::{time::{Instant, Duration}, rc::Rc};
-use glium::{backend::glutin::SimpleWindowBuilder, program};
+use std::{num::NonZeroU32, path::Path, time::{Instant, Duration}};
+use glium::{
+    Display,
+    Surface,
+    glutin::{
+        config::ConfigTemplateBuilder,
+        context::ContextAttributesBuilder,
+        display::GetGlDisplay,
+        prelude::*,
+        surface::{SurfaceAttributesBuilder, WindowSurface}
+    },
+    ProgramCreationError,
+    program,
+    texture::TextureCreationError
+};
+use glutin_winit::DisplayBuilder;
 use image::RgbaImage;
-use winit::{event_loop::{EventLoopBuilder, ControlFlow}, event::{WindowEvent, Event, StartCause::ResumeTimeReached}};
+use raw_window_handle::HasRawWindowHandle;
+use winit::{
+    dpi::PhysicalSize,
+    event_loop::{EventLoop, EventLoopBuilder, ControlFlow},
+    event::{WindowEvent, Event, StartCause::ResumeTimeReached},
+    window::{Window, WindowBuilder}
+};
 
-use crate::{sprite_batch::SpriteBatch, sprite::SpriteLoader};
+use crate::{
+    color::Color,
+    effect::{Shader, DEFAULT_FRAGMENT_SHADER, DEFAULT_VERTEX_SHADER},
+    renderer::{GliumBackend, Renderer, SoftwareRenderer},
+    sprite_batch::{SpriteBatch, SpriteBatchError}, 
+    sprite::{SpriteError, SpriteLoader}
+};
+
+/// How often `run` checks the files loaded with `SpriteLoader::load_sprite_file` for changes.
+const RELOAD_INTERVAL: Duration = Duration::from_millis(250);
 
 pub trait ApplicationContext {
     fn new() -> Self;
 
-    fn load(&mut self, sprite_loader: &mut SpriteLoader) { }
-    fn update(&mut self, delta_time: f32) { }
-    fn draw(&self, sprite_batch: &mut SpriteBatch) { }
+    /// Creates the loader passed to `load`. Override it to pack sprites into an atlas with `SpriteLoader::new_atlas`.
+    fn sprite_loader(&self) -> SpriteLoader {
+        SpriteLoader::new()
+    }
+
+    fn load(&mut self, _sprite_loader: &mut SpriteLoader) { }
+    /// Called before every frame. Sprites loaded, replaced or unloaded through `sprite_loader` are uploaded before `draw`.
+    fn update(&mut self, _delta_time: f32, _sprite_loader: &mut SpriteLoader) { }
+
+    /// Called when a file loaded with `SpriteLoader::load_sprite_file` changed on disk but could not be reloaded.
+    fn reload_failed(&mut self, path: &Path, error: SpriteError) {
+        eprintln!("failed to reload {}: {error}", path.display());
+    }
+
+    /// Called by `run` when the textures of the loaded sprites or render targets could not be created on the GPU.
+    /// The previous texture array is kept, so sprites keep showing their old pixels.
+    fn textures_failed(&mut self, error: TextureCreationError) {
+        eprintln!("failed to create the textures: {error}");
+    }
+
+    /// Called by `run` when a shader loaded with `SpriteLoader::load_shader` does not compile.
+    /// Draws using it fail with `SpriteBatchError::UnknownShader`, passed to `draw_failed`, while the other shaders keep working.
+    fn shader_failed(&mut self, _shader: Shader, error: ProgramCreationError) {
+        eprintln!("failed to compile a shader: {error}");
+    }
+
+    /// Called by `run` when `draw` returns an error. The frame is still shown, with whatever was drawn before the error.
+    fn draw_failed(&mut self, error: SpriteBatchError) {
+        eprintln!("failed to draw the frame: {error}");
+    }
+
+    fn draw(&self, _sprite_batch: &mut SpriteBatch, _renderer: &mut dyn Renderer) -> Result<(), SpriteBatchError> { 
+        Ok(())
+    }
+}
+
+/// Loads the white pixel sprite that `SpriteBatch::clear_color` and the shapes rely on, followed by the sprites of `context`.
+fn load<T: ApplicationContext>(context: &mut T) -> SpriteLoader {
+    let mut sprite_loader = context.sprite_loader();
+    sprite_loader.load_sprite(RgbaImage::from_raw(1u32, 1u32, vec![255, 255, 255, 255]).unwrap());
+    context.load(&mut sprite_loader);
+    sprite_loader.take_modified();
+    sprite_loader
+}
+
+/// Uploads the sprites, render targets and shaders of `sprite_loader`, reporting failures to `context`.
+fn update_backend<T: ApplicationContext>(context: &mut T, backend: &mut GliumBackend, sprite_loader: &SpriteLoader) {
+    if let Err(error) = backend.update_textures(sprite_loader) {
+        context.textures_failed(error);
+    }
+    for (shader, error) in backend.update_shaders(sprite_loader) {
+        context.shader_failed(shader, error);
+    }
+}
+
+fn reload_modified<T: ApplicationContext>(context: &mut T, sprite_loader: &mut SpriteLoader) {
+    for (path, error) in sprite_loader.reload_modified() {
+        context.reload_failed(&path, error);
+    }
+}
+
+/// Opens a window like glium's `SimpleWindowBuilder`, but with a stencil buffer for `MaskMode`.
+fn create_window(event_loop: &EventLoop<()>) -> (Window, Display<WindowSurface>) {
+    let window_builder = WindowBuilder::new()
+        .with_title("Sprite Batching")
+        .with_inner_size(PhysicalSize::new(800, 480));
+    let config_template_builder = ConfigTemplateBuilder::new().with_stencil_size(8);
+    let (window, gl_config) = DisplayBuilder::new()
+        .with_window_builder(Some(window_builder))
+        .build(event_loop, config_template_builder, |mut configs| configs.next().unwrap())
+        .unwrap();
+    let window = window.unwrap();
+
+    let (width, height): (u32, u32) = window.inner_size().into();
+    let surface_attributes = SurfaceAttributesBuilder::<WindowSurface>::new().build(
+        window.raw_window_handle(),
+        NonZeroU32::new(width).unwrap(),
+        NonZeroU32::new(height).unwrap()
+    );
+    let surface = unsafe { gl_config.display().create_window_surface(&gl_config, &surface_attributes).unwrap() };
+    let context_attributes = ContextAttributesBuilder::new().build(Some(window.raw_window_handle()));
+    let context = unsafe { gl_config.display().create_context(&gl_config, &context_attributes).unwrap() }
+        .make_current(&surface)
+        .unwrap();
+
+    (window, Display::from_context_surface(context, surface).unwrap())
 }
 
 pub fn run<T>() where T: ApplicationContext + 'static {
     let event_loop = EventLoopBuilder::new().build();
-    let (window, display) = SimpleWindowBuilder::new().build(&event_loop);
+    let (window, display) = create_window(&event_loop);
     let program = program!(
         &display,
         140 => {
-            vertex: include_str!("shaders/default.vert"),
-            fragment: include_str!("shaders/default.frag")
+            vertex: DEFAULT_VERTEX_SHADER,
+            fragment: DEFAULT_FRAGMENT_SHADER
         }
     ).unwrap();
 
     let mut context = T::new();
-
-    let mut sprite_loader = SpriteLoader::new();
-    sprite_loader.load_sprite(RgbaImage::from_raw(1u32, 1u32, vec![255, 255, 255, 255]).unwrap());
-    context.load(&mut sprite_loader);
+    let mut sprite_loader = load(&mut context);
     let texture_array = sprite_loader.create_texture_array(&display).unwrap();
 
-
-    let mut sprite_batch = SpriteBatch::new(
-        Rc::new(window),
-        Rc::new(display),
-        program,
-        texture_array
-    );
+    let mut backend = GliumBackend::new(display, program, texture_array);
+    update_backend(&mut context, &mut backend, &sprite_loader);
+    let mut sprite_batch = SpriteBatch::new(window.inner_size().into());
 
     let mut last_frame_instant = Instant::now();
+    let mut last_reload_instant = Instant::now();
     event_loop.run(
         move |event, _, control_flow| {
             match event {
-                Event::WindowEvent { event, .. } => {
-                    match event {
-                        WindowEvent::CloseRequested => {
-                            *control_flow = ControlFlow::Exit;
-                        },
-                        _ => ()
-                    }
+                Event::WindowEvent { event: WindowEvent::CloseRequested, .. } => {
+                    *control_flow = ControlFlow::Exit;
+                },
+                Event::WindowEvent { event: WindowEvent::Resized(size), .. } => {
+                    backend.display().resize(size.into());
+                    sprite_batch.camera.viewport = size.into();
                 },
                 Event::NewEvents(ResumeTimeReached { .. }) => {
                     let delta_time = last_frame_instant.elapsed().as_secs_f32();
                     last_frame_instant = Instant::now();
-                    context.update(delta_time);
-                    context.draw(&mut sprite_batch);
-                    sprite_batch.flush().unwrap();
+                    if last_reload_instant.elapsed() >= RELOAD_INTERVAL {
+                        last_reload_instant = Instant::now();
+                        reload_modified(&mut context, &mut sprite_loader);
+                    }
+
+                    context.update(delta_time, &mut sprite_loader);
+                    if sprite_loader.take_modified() {
+                        update_backend(&mut context, &mut backend, &sprite_loader);
+                    }
+
+                    let mut frame = backend.display().draw();
+                    frame.clear_all((0f32, 0f32, 0f32, 0f32), 0f32, 0i32);
+                    if let Err(error) = context.draw(&mut sprite_batch, &mut backend.renderer(&mut frame)) {
+                        context.draw_failed(error);
+                    }
+                    frame.finish().unwrap();
                 }
                 _ => ()
             }
@@ -66,4 +184,44 @@ pub fn run<T>() where T: ApplicationContext + 'static {
             }
         }
     );
-}
\ No newline at end of file
+}
+
+/// Runs an `ApplicationContext` without a window, drawing its frames with a `SoftwareRenderer`.
+pub struct HeadlessApplication<T: ApplicationContext> {
+    context: T,
+    sprite_loader: SpriteLoader,
+    sprite_batch: SpriteBatch<'static>,
+    renderer: SoftwareRenderer
+}
+
+impl <T: ApplicationContext> HeadlessApplication<T> {
+    pub fn new(width: u32, height: u32) -> Self {
+        let mut context = T::new();
+        let sprite_loader = load(&mut context);
+        let mut renderer = SoftwareRenderer::new(Vec::new(), width, height);
+        renderer.update_textures(&sprite_loader);
+        renderer.update_shaders(&sprite_loader);
+        Self { context, sprite_loader, sprite_batch: SpriteBatch::new((width, height)), renderer }
+    }
+
+    pub fn context(&self) -> &T {
+        &self.context
+    }
+
+    /// Reloads modified sprite files and updates the context.
+    pub fn update(&mut self, delta_time: f32) {
+        reload_modified(&mut self.context, &mut self.sprite_loader);
+        self.context.update(delta_time, &mut self.sprite_loader);
+        if self.sprite_loader.take_modified() {
+            self.renderer.update_textures(&self.sprite_loader);
+            self.renderer.update_shaders(&self.sprite_loader);
+        }
+    }
+
+    /// Clears the image and draws a frame onto it.
+    pub fn draw(&mut self) -> Result<&RgbaImage, SpriteBatchError> {
+        self.renderer.clear(Color::new(0f32, 0f32, 0f32, 0f32));
+        self.context.draw(&mut self.sprite_batch, &mut self.renderer)?;
+        Ok(self.renderer.image())
+    }
+}
@@ -0,0 +1,198 @@
+/// Packs rectangles into layers of a fixed size. Every layer is split into horizontal shelves,
+/// each as tall as the first rectangle placed on it, which are filled from left to right.
+/// Freed rectangles are reused whole by later rectangles that fit in them, the smallest first, and are freed whole again.
+/// A layer is reset once all of its rectangles are freed.
+#[derive(Clone, Debug)]
+pub(crate) struct ShelfPacker {
+    layer_dimensions: (u32, u32),
+    padding: u32,
+    layers: Vec<Layer>
+}
+
+#[derive(Clone, Debug, Default)]
+struct Layer {
+    shelves: Vec<Shelf>,
+    free_slots: Vec<((u32, u32), (u32, u32))>,
+    /// The position of every rectangle in the layer and the dimensions reserved for it,
+    /// which are those of the freed slot it reuses, if any.
+    allocations: Vec<((u32, u32), (u32, u32))>
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Shelf {
+    y: u32,
+    height: u32,
+    x: u32
+}
+
+impl ShelfPacker {
+    /// `padding` empty pixels are kept between neighbouring rectangles so linear filtering doesn't bleed.
+    pub fn new(layer_dimensions: (u32, u32), padding: u32) -> Self {
+        Self { layer_dimensions, padding, layers: Vec::new() }
+    }
+
+    pub fn layer_dimensions(&self) -> (u32, u32) {
+        self.layer_dimensions
+    }
+
+    /// Returns the layer and the bottom-left corner of the space reserved for a rectangle of `dimensions`,
+    /// or `None` if it is larger than a layer.
+    pub fn pack(&mut self, dimensions: (u32, u32)) -> Option<(u32, (u32, u32))> {
+        let (width, height) = dimensions;
+        let (layer_width, layer_height) = self.layer_dimensions;
+        if width > layer_width || height > layer_height {
+            return None;
+        }
+
+        let free_slot = self.layers.iter().enumerate().flat_map(
+            |(layer, value)| value.free_slots.iter().enumerate().map(move |(slot, value)| (layer, slot, value))
+        ).filter(
+            |(_, _, (_, slot_dimensions))| slot_dimensions.0 >= width && slot_dimensions.1 >= height
+        ).min_by_key(
+            |(_, _, (_, slot_dimensions))| slot_dimensions.0 * slot_dimensions.1
+        ).map(|(layer, slot, _)| (layer, slot));
+
+        if let Some((layer, slot)) = free_slot {
+            let layer_value = &mut self.layers[layer];
+            let (position, slot_dimensions) = layer_value.free_slots.swap_remove(slot);
+            layer_value.allocations.push((position, slot_dimensions));
+            return Some((layer as u32, position));
+        }
+
+        let best_shelf = self.layers.iter().enumerate().flat_map(
+            |(layer, value)| value.shelves.iter().enumerate().map(move |(shelf, value)| (layer, shelf, value))
+        ).filter(
+            |(_, _, shelf)| shelf.height >= height && shelf.x + width <= layer_width
+        ).min_by_key(
+            |(_, _, shelf)| shelf.height
+        ).map(|(layer, shelf, _)| (layer, shelf));
+
+        if let Some((layer, shelf)) = best_shelf {
+            let layer_value = &mut self.layers[layer];
+            let shelf = &mut layer_value.shelves[shelf];
+            let position = (shelf.x, shelf.y);
+            shelf.x += width + self.padding;
+            layer_value.allocations.push((position, dimensions));
+            return Some((layer as u32, position));
+        }
+
+        let free_layer = self.layers.iter().position(
+            |layer| self.next_shelf_y(layer) + height <= layer_height
+        );
+        let layer = free_layer.unwrap_or_else(
+            || {
+                self.layers.push(Layer::default());
+                self.layers.len() - 1
+            }
+        );
+
+        let y = self.next_shelf_y(&self.layers[layer]);
+        let layer_value = &mut self.layers[layer];
+        layer_value.shelves.push(Shelf { y, height, x: width + self.padding });
+        layer_value.allocations.push(((0, y), dimensions));
+        Some((layer as u32, (0, y)))
+    }
+
+    /// Releases all the space reserved for the rectangle `pack` placed at `position`.
+    pub fn free(&mut self, layer: u32, position: (u32, u32)) {
+        let Some(layer) = self.layers.get_mut(layer as usize) else {
+            return;
+        };
+        let Some(allocation) = layer.allocations.iter().position(|(allocated, _)| *allocated == position) else {
+            return;
+        };
+
+        let slot = layer.allocations.swap_remove(allocation);
+        if layer.allocations.is_empty() {
+            *layer = Layer::default();
+        } else {
+            layer.free_slots.push(slot);
+        }
+    }
+
+    fn next_shelf_y(&self, layer: &Layer) -> u32 {
+        layer.shelves.last().map_or(0, |shelf| shelf.y + shelf.height + self.padding)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ShelfPacker;
+
+    #[test]
+    fn rectangles_fill_shelves_from_left_to_right() {
+        let mut packer = ShelfPacker::new((16, 16), 1);
+        assert_eq!(packer.pack((4, 4)), Some((0, (0, 0))));
+        assert_eq!(packer.pack((4, 3)), Some((0, (5, 0))));
+        assert_eq!(packer.pack((4, 6)), Some((0, (0, 5))));
+        assert_eq!(packer.pack((2, 4)), Some((0, (10, 0))));
+        assert_eq!(packer.pack((6, 6)), Some((0, (5, 5))));
+    }
+
+    #[test]
+    fn full_shelves_start_a_new_one() {
+        let mut packer = ShelfPacker::new((8, 8), 0);
+        assert_eq!(packer.pack((6, 2)), Some((0, (0, 0))));
+        assert_eq!(packer.pack((4, 2)), Some((0, (0, 2))));
+        assert_eq!(packer.pack((2, 2)), Some((0, (6, 0))));
+    }
+
+    #[test]
+    fn full_layers_spill_into_a_new_one() {
+        let mut packer = ShelfPacker::new((8, 8), 0);
+        assert_eq!(packer.pack((8, 6)), Some((0, (0, 0))));
+        assert_eq!(packer.pack((4, 4)), Some((1, (0, 0))));
+        assert_eq!(packer.pack((8, 2)), Some((0, (0, 6))));
+    }
+
+    #[test]
+    fn freed_rectangles_are_reused() {
+        let mut packer = ShelfPacker::new((8, 8), 0);
+        assert_eq!(packer.pack((4, 4)), Some((0, (0, 0))));
+        assert_eq!(packer.pack((4, 4)), Some((0, (4, 0))));
+        packer.free(0, (0, 0));
+        assert_eq!(packer.pack((5, 4)), Some((0, (0, 4))));
+        assert_eq!(packer.pack((3, 3)), Some((0, (0, 0))));
+    }
+
+    #[test]
+    fn reused_slots_are_freed_whole() {
+        let mut packer = ShelfPacker::new((8, 8), 0);
+        assert_eq!(packer.pack((8, 4)), Some((0, (0, 0))));
+        assert_eq!(packer.pack((8, 4)), Some((0, (0, 4))));
+        packer.free(0, (0, 0));
+        assert_eq!(packer.pack((2, 2)), Some((0, (0, 0))));
+        packer.free(0, (0, 0));
+        assert_eq!(packer.pack((8, 4)), Some((0, (0, 0))));
+    }
+
+    #[test]
+    fn the_smallest_fitting_slot_is_reused() {
+        let mut packer = ShelfPacker::new((8, 8), 0);
+        assert_eq!(packer.pack((8, 4)), Some((0, (0, 0))));
+        assert_eq!(packer.pack((4, 4)), Some((0, (0, 4))));
+        assert_eq!(packer.pack((4, 4)), Some((0, (4, 4))));
+        packer.free(0, (0, 0));
+        packer.free(0, (0, 4));
+        assert_eq!(packer.pack((3, 3)), Some((0, (0, 4))));
+        assert_eq!(packer.pack((5, 3)), Some((0, (0, 0))));
+    }
+
+    #[test]
+    fn emptied_layers_are_reset() {
+        let mut packer = ShelfPacker::new((8, 8), 0);
+        assert_eq!(packer.pack((8, 4)), Some((0, (0, 0))));
+        assert_eq!(packer.pack((8, 4)), Some((0, (0, 4))));
+        packer.free(0, (0, 0));
+        packer.free(0, (0, 4));
+        assert_eq!(packer.pack((8, 8)), Some((0, (0, 0))));
+    }
+
+    #[test]
+    fn rectangles_larger_than_a_layer_are_rejected() {
+        let mut packer = ShelfPacker::new((8, 8), 0);
+        assert_eq!(packer.pack((9, 1)), None);
+        assert_eq!(packer.pack((1, 9)), None);
+        assert_eq!(packer.pack((8, 8)), Some((0, (0, 0))));
+    }
+}
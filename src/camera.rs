@@ -0,0 +1,132 @@
+use defaults::Defaults;
+use glium::Rect;
+
+use crate::math::{Matrix4x4, Vector2};
+
+/// A fixed logical resolution that is scaled uniformly to fit the viewport, leaving letterbox or pillarbox bars.
+#[derive(Clone, Copy, Debug)]
+pub struct VirtualResolution {
+    pub width: u32,
+    pub height: u32,
+    /// Only scale by whole numbers so every logical pixel covers the same number of screen pixels,
+    /// unless the viewport is smaller than the virtual resolution.
+    pub integer_scaling: bool
+}
+
+impl VirtualResolution {
+    pub fn new(width: u32, height: u32, integer_scaling: bool) -> Self {
+        Self { width, height, integer_scaling }
+    }
+
+    pub fn scale(&self, viewport: (u32, u32)) -> f32 {
+        let scale = (viewport.0 as f32 / self.width as f32).min(viewport.1 as f32 / self.height as f32);
+        if self.integer_scaling && scale >= 1f32 {
+            scale.floor()
+        } else {
+            scale
+        }
+    }
+}
+
+/// A view onto the world. `position` is the world point shown at the centre of the viewport,
+/// `zoom` scales the world and `rotation` turns the view counterclockwise around `position`.
+#[derive(Clone, Copy, Debug, Defaults)]
+pub struct Camera2D {
+    pub position: Vector2,
+    #[def = "1f32"]
+    pub zoom: f32,
+    pub rotation: f32,
+    /// The size of the surface the camera draws onto, in pixels.
+    #[def = "(1u32, 1u32)"]
+    pub viewport: (u32, u32),
+    /// When set, the camera shows this many pixels centred in the viewport instead of the whole viewport.
+    pub virtual_resolution: Option<VirtualResolution>
+}
+
+impl Camera2D {
+    pub fn new(viewport: (u32, u32)) -> Self {
+        Self { viewport, ..Default::default() }
+    }
+
+    /// Maps world space to logical pixels relative to the centre of the view, with y pointing up.
+    pub fn view_matrix(&self) -> Matrix4x4 {
+        Matrix4x4::new_scaling(self.zoom, self.zoom, 1f32)
+            * Matrix4x4::new_rotation(-self.rotation)
+            * Matrix4x4::new_translation(-self.position.x, -self.position.y, 0f32)
+    }
+
+    pub fn inverse_view_matrix(&self) -> Matrix4x4 {
+        Matrix4x4::new_translation(self.position.x, self.position.y, 0f32)
+            * Matrix4x4::new_rotation(self.rotation)
+            * Matrix4x4::new_scaling(1f32 / self.zoom, 1f32 / self.zoom, 1f32)
+    }
+
+    /// Maps world space to clip space, covering the `letterbox` of the viewport.
+    pub fn projection(&self) -> Matrix4x4 {
+        let logical_size = self.logical_size();
+        Matrix4x4::new_scaling(2f32 / logical_size.x, 2f32 / logical_size.y, 1f32) * self.view_matrix()
+    }
+
+    /// The area of the viewport the camera draws onto, in pixels from its bottom-left corner.
+    /// This is the whole viewport unless a virtual resolution is set.
+    pub fn letterbox(&self) -> Rect {
+        let Some(virtual_resolution) = self.virtual_resolution else {
+            return Rect { left: 0, bottom: 0, width: self.viewport.0, height: self.viewport.1 };
+        };
+
+        let scale = virtual_resolution.scale(self.viewport);
+        let width = (virtual_resolution.width as f32 * scale).round() as u32;
+        let height = (virtual_resolution.height as f32 * scale).round() as u32;
+        Rect {
+            left: self.viewport.0.saturating_sub(width) / 2,
+            bottom: self.viewport.1.saturating_sub(height) / 2,
+            width,
+            height
+        }
+    }
+
+    /// Converts a position in pixels from the top-left corner of the viewport, such as the mouse position, into world space.
+    pub fn screen_to_world(&self, screen_position: Vector2) -> Vector2 {
+        let [x, y, _, _] = (self.inverse_view_matrix() * self.logical_from_screen()).transform([screen_position.x, screen_position.y, 0f32, 1f32]);
+        Vector2::new(x, y)
+    }
+
+    /// Converts a world position into pixels from the top-left corner of the viewport.
+    pub fn world_to_screen(&self, world_position: Vector2) -> Vector2 {
+        let [x, y, _, _] = (self.screen_from_logical() * self.view_matrix()).transform([world_position.x, world_position.y, 0f32, 1f32]);
+        Vector2::new(x, y)
+    }
+
+    /// The size of the visible area in world units.
+    pub fn visible_size(&self) -> Vector2 {
+        self.logical_size() * (1f32 / self.zoom)
+    }
+
+    fn logical_size(&self) -> Vector2 {
+        match self.virtual_resolution {
+            Some(virtual_resolution) => Vector2::new(virtual_resolution.width as f32, virtual_resolution.height as f32),
+            None => Vector2::new(self.viewport.0 as f32, self.viewport.1 as f32)
+        }
+    }
+
+    /// Maps pixels relative to the centre of the logical area, with y pointing up, to pixels from the top-left corner of the viewport.
+    fn screen_from_logical(&self) -> Matrix4x4 {
+        let letterbox = self.letterbox();
+        let scale = letterbox.width as f32 / self.logical_size().x;
+        Matrix4x4::new_translation(
+            letterbox.left as f32 + letterbox.width as f32 / 2f32,
+            self.viewport.1 as f32 - letterbox.bottom as f32 - letterbox.height as f32 / 2f32,
+            0f32
+        ) * Matrix4x4::new_scaling(scale, -scale, 1f32)
+    }
+
+    fn logical_from_screen(&self) -> Matrix4x4 {
+        let letterbox = self.letterbox();
+        let scale = letterbox.width as f32 / self.logical_size().x;
+        Matrix4x4::new_scaling(1f32 / scale, -1f32 / scale, 1f32) * Matrix4x4::new_translation(
+            -(letterbox.left as f32 + letterbox.width as f32 / 2f32),
+            -(self.viewport.1 as f32 - letterbox.bottom as f32 - letterbox.height as f32 / 2f32),
+            0f32
+        )
+    }
+}
@@ -0,0 +1,118 @@
+pub const DEFAULT_VERTEX_SHADER: &str = include_str!("shaders/default.vert");
+pub const DEFAULT_FRAGMENT_SHADER: &str = include_str!("shaders/default.frag");
+
+/// What the `SoftwareRenderer` knows about a pixel when it runs a `SoftwareFragment`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fragment {
+    pub uv: [f32; 2],
+    pub color: [f32; 4],
+    /// The filtered texel at `uv`, which the default shader multiplies by `color`.
+    pub texel: [f32; 4],
+    /// The pixel being drawn, from the top-left corner of the surface.
+    pub position: (u32, u32)
+}
+
+/// A CPU version of a fragment shader, used by the `SoftwareRenderer` since it cannot run GLSL.
+pub type SoftwareFragment = fn(&Fragment, &Uniforms) -> [f32; 4];
+
+/// The source of a shader program. Its vertex shader receives the same attributes as the default one,
+/// and its fragment shader the `textures` sampler, the `resolution` of the viewport in pixels, the `alpha_cutoff` below which
+/// fragments should be discarded for masks to work, and the `Uniforms` of its `Effect`.
+#[derive(Clone, Debug)]
+pub struct ShaderSource {
+    pub vertex: String,
+    pub fragment: String,
+    /// Without it, the `SoftwareRenderer` draws the effect like the default shader.
+    pub software_fragment: Option<SoftwareFragment>
+}
+
+impl ShaderSource {
+    pub fn new(vertex: impl Into<String>, fragment: impl Into<String>) -> Self {
+        Self { vertex: vertex.into(), fragment: fragment.into(), software_fragment: None }
+    }
+
+    /// A shader that only replaces the fragment shader, keeping the default vertex shader.
+    pub fn from_fragment(fragment: impl Into<String>) -> Self {
+        Self::new(DEFAULT_VERTEX_SHADER, fragment)
+    }
+
+    pub fn with_software_fragment(self, software_fragment: SoftwareFragment) -> Self {
+        Self { software_fragment: Some(software_fragment), ..self }
+    }
+}
+
+/// A shader program loaded with `SpriteLoader::load_shader`, compiled by the renderer once the loader is marked as modified.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Shader {
+    id: u32
+}
+
+impl Shader {
+    pub(crate) fn new(id: u32) -> Self {
+        Self { id }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UniformValue {
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+    Int(i32),
+    UnsignedInt(u32),
+    Bool(bool),
+    /// A column-major matrix.
+    Matrix4([[f32; 4]; 4])
+}
+
+/// Named values passed to the uniforms of a shader with the same names.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Uniforms {
+    values: Vec<(String, UniformValue)>
+}
+
+impl Uniforms {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets a uniform, replacing its previous value.
+    pub fn set(&mut self, name: &str, value: UniformValue) {
+        match self.values.iter_mut().find(|(existing, _)| existing == name) {
+            Some((_, existing)) => *existing = value,
+            None => self.values.push((name.to_owned(), value))
+        }
+    }
+
+    /// Like `set`, for building uniforms in a single expression.
+    pub fn with(mut self, name: &str, value: UniformValue) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<UniformValue> {
+        self.values.iter().find(|(existing, _)| existing == name).map(|(_, value)| *value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, UniformValue)> {
+        self.values.iter().map(|(name, value)| (name.as_str(), *value))
+    }
+}
+
+/// A shader and the values of its uniforms, applied to the sprites drawn after `SpriteBatch::set_effect`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Effect {
+    pub shader: Shader,
+    pub uniforms: Uniforms
+}
+
+impl Effect {
+    pub fn new(shader: Shader, uniforms: Uniforms) -> Self {
+        Self { shader, uniforms }
+    }
+}
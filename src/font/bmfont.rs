@@ -0,0 +1,206 @@
+use super::FontError;
+
+/// The contents of an AngelCode BMFont descriptor, in the units and coordinates of the file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Descriptor {
+    pub line_height: u32,
+    pub base: u32,
+    /// The page image files, indexed by page id.
+    pub pages: Vec<String>,
+    pub chars: Vec<CharDescriptor>,
+    pub kernings: Vec<(u32, u32, i32)>
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct CharDescriptor {
+    pub id: u32,
+    /// The top-left corner of the glyph in its page.
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// From the pen position to the top-left corner of the glyph, with y pointing down.
+    pub x_offset: i32,
+    pub y_offset: i32,
+    pub x_advance: i32,
+    pub page: u32
+}
+
+impl Descriptor {
+    /// Parses the binary format if `data` starts with its signature, and the text format otherwise.
+    pub fn parse(data: &[u8]) -> Result<Self, FontError> {
+        if data.starts_with(b"BMF") {
+            parse_binary(data)
+        } else {
+            let text = std::str::from_utf8(data).map_err(|_| FontError::Parse("the descriptor is not valid UTF-8".to_owned()))?;
+            parse_text(text)
+        }
+    }
+}
+
+/// The most pages a text font may declare, so a corrupt page id cannot allocate a huge page list.
+const MAX_PAGES: usize = 256;
+
+fn parse_text(text: &str) -> Result<Descriptor, FontError> {
+    let mut descriptor = Descriptor::default();
+    // The page count given by `common`, which page ids must stay below.
+    let mut page_count = MAX_PAGES;
+    for (line_number, line) in text.lines().enumerate() {
+        let mut tokens = tokenize(line).into_iter();
+        let Some(tag) = tokens.next() else {
+            continue;
+        };
+
+        let attributes: Vec<(String, String)> = tokens.filter_map(
+            |token| token.split_once('=').map(|(key, value)| (key.to_owned(), value.to_owned()))
+        ).collect();
+        let attribute = |key: &str| attributes.iter().find(|(existing, _)| existing == key).map(|(_, value)| value.as_str());
+        let number = |key: &str| -> Result<i32, FontError> {
+            let value = attribute(key).ok_or_else(|| FontError::Parse(format!("line {}: `{tag}` has no `{key}`", line_number + 1)))?;
+            value.parse().map_err(|_| FontError::Parse(format!("line {}: `{key}={value}` is not a number", line_number + 1)))
+        };
+        let unsigned = |key: &str| number(key).map(|value| value.max(0) as u32);
+
+        match tag.as_str() {
+            "common" => {
+                descriptor.line_height = unsigned("lineHeight")?;
+                descriptor.base = unsigned("base")?;
+                if attribute("pages").is_some() {
+                    page_count = (unsigned("pages")? as usize).min(MAX_PAGES);
+                }
+            },
+            "page" => {
+                let id = unsigned("id")? as usize;
+                if id >= page_count {
+                    return Err(FontError::Parse(format!("line {}: page {id} is out of range, the font has {page_count} pages", line_number + 1)));
+                }
+                let file = attribute("file").ok_or_else(|| FontError::Parse(format!("line {}: `page` has no `file`", line_number + 1)))?;
+                if descriptor.pages.len() <= id {
+                    descriptor.pages.resize(id + 1, String::new());
+                }
+                descriptor.pages[id] = file.to_owned();
+            },
+            "char" => descriptor.chars.push(
+                CharDescriptor {
+                    id: unsigned("id")?,
+                    x: unsigned("x")?,
+                    y: unsigned("y")?,
+                    width: unsigned("width")?,
+                    height: unsigned("height")?,
+                    x_offset: number("xoffset")?,
+                    y_offset: number("yoffset")?,
+                    x_advance: number("xadvance")?,
+                    page: unsigned("page")?
+                }
+            ),
+            "kerning" => descriptor.kernings.push((unsigned("first")?, unsigned("second")?, number("amount")?)),
+            _ => ()
+        }
+    }
+
+    Ok(descriptor)
+}
+
+/// Splits a line of the text format at the spaces outside of quotes, removing the quotes.
+fn tokenize(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+    for character in line.chars() {
+        match character {
+            '"' => quoted = !quoted,
+            ' ' | '\t' if !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            },
+            _ => token.push(character)
+        }
+    }
+
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+/// Reads the little-endian fields of the binary format.
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize
+}
+
+impl <'a> Reader<'a> {
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], FontError> {
+        let bytes = self.data.get(self.position..self.position + count)
+            .ok_or_else(|| FontError::Parse(format!("the binary descriptor ends early at byte {}", self.data.len())))?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, FontError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, FontError> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn i16(&mut self) -> Result<i16, FontError> {
+        self.u16().map(|value| value as i16)
+    }
+
+    fn u32(&mut self) -> Result<u32, FontError> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+fn parse_binary(data: &[u8]) -> Result<Descriptor, FontError> {
+    let mut reader = Reader { data, position: 3 };
+    let version = reader.u8()?;
+    if version != 3 {
+        return Err(FontError::Parse(format!("binary descriptor version {version} is not supported")));
+    }
+
+    let mut descriptor = Descriptor::default();
+    while reader.position < data.len() {
+        let block_type = reader.u8()?;
+        let size = reader.u32()? as usize;
+        let mut block = Reader { data: reader.bytes(size)?, position: 0 };
+        match block_type {
+            2 => {
+                descriptor.line_height = block.u16()? as u32;
+                descriptor.base = block.u16()? as u32;
+            },
+            3 => descriptor.pages = block.data.split(|byte| *byte == 0)
+                .filter(|name| !name.is_empty())
+                .map(|name| String::from_utf8_lossy(name).into_owned())
+                .collect(),
+            4 => while block.position < size {
+                descriptor.chars.push(
+                    CharDescriptor {
+                        id: block.u32()?,
+                        x: block.u16()? as u32,
+                        y: block.u16()? as u32,
+                        width: block.u16()? as u32,
+                        height: block.u16()? as u32,
+                        x_offset: block.i16()? as i32,
+                        y_offset: block.i16()? as i32,
+                        x_advance: block.i16()? as i32,
+                        page: block.u8()? as u32
+                    }
+                );
+                block.u8()?;
+            },
+            5 => while block.position < size {
+                descriptor.kernings.push((block.u32()?, block.u32()?, block.i16()? as i32));
+            },
+            _ => ()
+        }
+    }
+
+    Ok(descriptor)
+}
@@ -0,0 +1,304 @@
+use std::ops::Range;
+
+use defaults::Defaults;
+use unicode_linebreak::{linebreaks, BreakOpportunity};
+
+use crate::{color::Color, math::Vector2, sprite_batch::DrawData};
+use super::{Font, Glyph, LineMetrics};
+
+/// How the lines of a text are placed horizontally.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+    /// Widens the spaces of every wrapped line so it fills the width. Lines ending a paragraph stay left aligned.
+    Justify
+}
+
+#[derive(Clone, Debug, Defaults)]
+pub struct TextOptions {
+    #[def = "Color::new(1f32, 1f32, 1f32, 1f32)"]
+    pub color: Color,
+    #[def = "1f32"]
+    pub scale: f32,
+    /// Lines longer than this are wrapped at the nearest Unicode line break opportunity before it,
+    /// or between characters if a single word is longer.
+    pub max_width: Option<f32>,
+    /// Lines are aligned within `max_width`, or within the widest line if it is `None`.
+    pub align: TextAlign,
+    /// Multiplies the distance between consecutive lines.
+    #[def = "1f32"]
+    pub line_spacing: f32,
+    /// Reads `[color=#rgb]`, `[color=#rgba]`, `[color=#rrggbb]` and `[color=#rrggbbaa]` tags, which replace the colour
+    /// until the matching `[/color]`, and `[scale=1.5]` tags, which multiply the scale until the matching `[/scale]`.
+    /// `[[` is a literal `[`, and anything else in brackets, including scales that are not positive and finite, is kept as text.
+    pub markup: bool
+}
+
+#[derive(Clone, Copy, Debug)]
+struct StyledChar {
+    character: char,
+    color: Color,
+    scale: f32
+}
+
+#[derive(Clone, Copy, Debug)]
+struct PlacedGlyph {
+    character: char,
+    scale: f32,
+    glyph: Glyph,
+    /// From the top-left corner of the text to the pen position on the baseline.
+    pen: Vector2,
+    color: Color
+}
+
+/// A text broken into lines and aligned, ready to be measured or drawn.
+#[derive(Clone, Debug, Default)]
+pub struct TextLayout {
+    glyphs: Vec<PlacedGlyph>,
+    line_count: usize,
+    size: Vector2
+}
+
+impl TextLayout {
+    pub fn new<F: Font + ?Sized>(font: &F, text: &str, options: &TextOptions) -> Self {
+        let (characters, plain_text) = if options.markup {
+            parse_markup(text, options.color, options.scale)
+        } else {
+            (text.chars().map(|character| StyledChar { character, color: options.color, scale: options.scale }).collect(), text.to_owned())
+        };
+
+        // The break opportunity before every character, and after the last one.
+        let mut breaks = vec![None; characters.len() + 1];
+        let character_indices: Vec<usize> = plain_text.char_indices().map(|(index, _)| index).collect();
+        for (byte_index, opportunity) in linebreaks(&plain_text) {
+            let index = character_indices.binary_search(&byte_index).unwrap_or(characters.len());
+            breaks[index] = Some(opportunity);
+        }
+
+        let glyphs: Vec<Option<Glyph>> = characters.iter().map(
+            |styled| if styled.character.is_control() { None } else { font.scaled_glyph(styled.character, styled.scale) }
+        ).collect();
+        // Lines and whether they were wrapped rather than ended by a mandatory break or the end of the text.
+        let mut lines: Vec<(Range<usize>, bool)> = Vec::new();
+        let mut start = 0;
+        let mut candidate = None;
+        let mut index = 0;
+        // Placed from `start` up to `index`, so the width of the line grows with every character.
+        let mut pen = Pen::default();
+        while index < characters.len() {
+            if index > start && breaks[index] == Some(BreakOpportunity::Mandatory) {
+                lines.push((start..index, false));
+                (start, candidate, pen) = (index, None, Pen::default());
+                continue;
+            }
+            if index > start && breaks[index] == Some(BreakOpportunity::Allowed) {
+                candidate = Some(index);
+            }
+
+            let mut next = pen;
+            next.advance(font, characters[index], glyphs[index]);
+            let overflows = options.max_width.is_some_and(
+                |max_width| !characters[index].character.is_whitespace() && next.width > max_width
+            );
+            if overflows && index > start {
+                let end = candidate.unwrap_or(index);
+                lines.push((start..end, true));
+                (start, candidate, index, pen) = (end, None, end, Pen::default());
+                continue;
+            }
+
+            pen = next;
+            index += 1;
+        }
+        if start < characters.len() {
+            lines.push((start..characters.len(), false));
+        }
+        if characters.last().is_some_and(|styled| is_newline(styled.character)) {
+            lines.push((characters.len()..characters.len(), false));
+        }
+
+        let placed_lines: Vec<(Vec<f32>, f32)> = lines.iter().map(|(range, _)| place(font, &characters[range.clone()], &glyphs[range.clone()])).collect();
+        let width = options.max_width.unwrap_or(placed_lines.iter().map(|(_, width)| *width).fold(0f32, f32::max));
+        let mut layout = Self { glyphs: Vec::with_capacity(characters.len()), line_count: lines.len(), size: Vector2::new(width, 0f32) };
+        let mut top = 0f32;
+        for (line_index, ((range, wrapped), (pens, line_width))) in lines.iter().zip(placed_lines).enumerate() {
+            // Empty lines keep the height of the line that ended before them.
+            let scale = characters[..range.end].last().map_or(options.scale, |styled| styled.scale);
+            let metrics = characters[range.clone()].iter().map(|styled| font.line_metrics(styled.scale)).fold(
+                font.line_metrics(scale),
+                |a, b| LineMetrics { line_height: a.line_height.max(b.line_height), base: a.base.max(b.base) }
+            );
+
+            let content_end = range.start + characters[range.clone()].iter().rposition(|styled| !styled.character.is_whitespace()).map_or(0, |end| end + 1);
+            let spaces = characters[range.start..content_end].iter().filter(|styled| styled.character.is_whitespace()).count();
+            let (offset, spacing) = match options.align {
+                TextAlign::Left => (0f32, 0f32),
+                TextAlign::Center => ((width - line_width) / 2f32, 0f32),
+                TextAlign::Right => (width - line_width, 0f32),
+                TextAlign::Justify if *wrapped && spaces > 0 => (0f32, (width - line_width).max(0f32) / spaces as f32),
+                TextAlign::Justify => (0f32, 0f32)
+            };
+
+            let mut extra = 0f32;
+            for (index, pen) in range.clone().zip(pens) {
+                if let Some(glyph) = glyphs[index] {
+                    let StyledChar { character, color, scale } = characters[index];
+                    layout.glyphs.push(
+                        PlacedGlyph { character, scale, glyph, pen: Vector2::new(offset + pen + extra, -(top + metrics.base)), color }
+                    );
+                }
+                if index < content_end && characters[index].character.is_whitespace() {
+                    extra += spacing;
+                }
+            }
+
+            let is_last = line_index + 1 == layout.line_count;
+            top += if is_last { metrics.line_height } else { metrics.line_height * options.line_spacing };
+        }
+
+        layout.size.y = top;
+        layout
+    }
+
+    /// The size of `text` laid out with `options`, as given by `size`.
+    pub fn measure<F: Font + ?Sized>(font: &F, text: &str, options: &TextOptions) -> Vector2 {
+        Self::new(font, text, options).size()
+    }
+
+    /// The width and height of the text. The width is `max_width` if it is set.
+    pub fn size(&self) -> Vector2 {
+        self.size
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_count
+    }
+
+    /// The layout with its glyphs looked up again in `font`, which may have moved or evicted them since the text
+    /// was laid out. Glyphs the font no longer has are left out.
+    pub(crate) fn refresh_glyphs<F: Font + ?Sized>(&self, font: &F) -> Self {
+        let glyphs = self.glyphs.iter().filter_map(
+            |placed| font.scaled_glyph(placed.character, placed.scale).map(|glyph| PlacedGlyph { glyph, ..*placed })
+        ).collect();
+        Self { glyphs, ..*self }
+    }
+
+    /// The glyph quads of the text, with its top-left corner at `position`.
+    pub fn draw_data(&self, position: Vector2) -> Vec<DrawData> {
+        self.glyphs.iter()
+            .filter(|placed| placed.glyph.source.width > 0f32 && placed.glyph.source.height > 0f32)
+            .map(
+                |placed| DrawData {
+                    sprite: placed.glyph.sprite,
+                    source: Some(placed.glyph.source),
+                    position: position + placed.pen + placed.glyph.offset,
+                    scale: Vector2::ONE * placed.glyph.scale,
+                    color: placed.color,
+                    ..Default::default()
+                }
+            )
+            .collect()
+    }
+}
+
+fn is_newline(character: char) -> bool {
+    matches!(character, '\n' | '\r' | '\u{0B}' | '\u{0C}' | '\u{85}' | '\u{2028}' | '\u{2029}')
+}
+
+/// The pen moving along a line, with kerning between characters of the same scale.
+#[derive(Clone, Copy, Debug, Default)]
+struct Pen {
+    x: f32,
+    /// The width of the line so far, without its trailing whitespace.
+    width: f32,
+    /// The last character with a glyph.
+    previous: Option<StyledChar>
+}
+
+impl Pen {
+    /// Moves the pen past `styled` and returns where its glyph is placed.
+    fn advance<F: Font + ?Sized>(&mut self, font: &F, styled: StyledChar, glyph: Option<Glyph>) -> f32 {
+        let Some(glyph) = glyph else {
+            return self.x;
+        };
+
+        if let Some(previous) = self.previous.filter(|previous| previous.scale == styled.scale) {
+            self.x += font.scaled_kerning(previous.character, styled.character, styled.scale);
+        }
+        let position = self.x;
+        self.x += glyph.advance;
+        if !styled.character.is_whitespace() {
+            self.width = self.x;
+        }
+        self.previous = Some(styled);
+        position
+    }
+}
+
+/// The pen position of every character from the start of the line, and the width of the line without its trailing whitespace.
+fn place<F: Font + ?Sized>(font: &F, characters: &[StyledChar], glyphs: &[Option<Glyph>]) -> (Vec<f32>, f32) {
+    let mut pen = Pen::default();
+    let pens = characters.iter().zip(glyphs).map(|(styled, glyph)| pen.advance(font, *styled, *glyph)).collect();
+    (pens, pen.width)
+}
+
+/// Splits `text` into its characters with their style and the text without markup.
+fn parse_markup(text: &str, color: Color, scale: f32) -> (Vec<StyledChar>, String) {
+    let mut characters = Vec::with_capacity(text.len());
+    let mut plain_text = String::with_capacity(text.len());
+    let mut colors = vec![color];
+    let mut scales = vec![scale];
+    let mut remaining = text;
+    while let Some(character) = remaining.chars().next() {
+        if let Some(rest) = remaining.strip_prefix("[[") {
+            characters.push(StyledChar { character: '[', color: *colors.last().unwrap(), scale: *scales.last().unwrap() });
+            plain_text.push('[');
+            remaining = rest;
+            continue;
+        }
+
+        let tag = remaining.strip_prefix('[').and_then(|rest| rest.split_once(']'));
+        let consumed = match tag {
+            Some(("/color", _)) if colors.len() > 1 => colors.pop().is_some(),
+            Some(("/scale", _)) if scales.len() > 1 => scales.pop().is_some(),
+            Some((tag, _)) => match tag.split_once('=') {
+                Some(("color", value)) => parse_color(value).map(|color| colors.push(color)).is_some(),
+                Some(("scale", value)) => value.parse::<f32>().ok()
+                    .map(|value| scales.last().unwrap() * value)
+                    .filter(|scale| scale.is_finite() && *scale > 0f32)
+                    .map(|scale| scales.push(scale))
+                    .is_some(),
+                _ => false
+            },
+            None => false
+        };
+        if let (true, Some((_, rest))) = (consumed, tag) {
+            remaining = rest;
+            continue;
+        }
+
+        characters.push(StyledChar { character, color: *colors.last().unwrap(), scale: *scales.last().unwrap() });
+        plain_text.push(character);
+        remaining = &remaining[character.len_utf8()..];
+    }
+
+    (characters, plain_text)
+}
+
+/// Reads `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`.
+fn parse_color(value: &str) -> Option<Color> {
+    let digits = value.strip_prefix('#')?;
+    if !digits.chars().all(|digit| digit.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let channels: Vec<f32> = match digits.len() {
+        3 | 4 => digits.chars().map(|digit| digit.to_digit(16).unwrap() as f32 / 15f32).collect(),
+        6 | 8 => (0..digits.len()).step_by(2).map(|index| u8::from_str_radix(&digits[index..index + 2], 16).unwrap() as f32 / 255f32).collect(),
+        _ => return None
+    };
+    Some(Color::new(channels[0], channels[1], channels[2], channels.get(3).copied().unwrap_or(1f32)))
+}
@@ -0,0 +1,202 @@
+mod bmfont;
+mod layout;
+mod truetype;
+
+pub use layout::{TextAlign, TextLayout, TextOptions};
+pub use truetype::TrueTypeFont;
+
+use std::{collections::HashMap, error::Error, fmt::{self, Formatter}, fs, io, path::Path};
+
+use crate::{
+    color::Color,
+    math::{Rectangle, Vector2},
+    render_target::RenderTargetWrite,
+    sprite::{Sprite, SpriteError, SpriteLoader},
+    sprite_batch::DrawData
+};
+use bmfont::Descriptor;
+
+#[derive(Debug)]
+pub enum FontError {
+    Io(io::Error),
+    /// The font file is malformed.
+    Parse(String),
+    /// A page image could not be loaded.
+    Sprite(SpriteError),
+    /// The glyph of the character did not fit in the atlas next to the glyphs of the current segment, so it was left out.
+    AtlasFull(char)
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "failed to read the font: {error}"),
+            Self::Parse(message) => write!(f, "failed to parse the font: {message}"),
+            Self::Sprite(error) => write!(f, "failed to load a font page: {error}"),
+            Self::AtlasFull(character) => write!(f, "the glyph atlas has no room for {character:?}")
+        }
+    }
+}
+
+impl Error for FontError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Sprite(error) => Some(error),
+            Self::Parse(_) | Self::AtlasFull(_) => None
+        }
+    }
+}
+
+/// Where a character is drawn from and how it is placed, in pixels of the font.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Glyph {
+    pub sprite: Sprite,
+    /// The part of `sprite` covered by the glyph, from its bottom-left corner.
+    pub source: Rectangle,
+    /// From the pen position on the baseline to the bottom-left corner of the glyph.
+    pub offset: Vector2,
+    /// How far the pen moves right after the glyph.
+    pub advance: f32,
+    /// How much `source` is magnified when drawn.
+    pub scale: f32
+}
+
+/// The vertical metrics of a font, in pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LineMetrics {
+    /// The distance between the baselines of consecutive lines.
+    pub line_height: f32,
+    /// The distance from the top of a line to its baseline.
+    pub base: f32
+}
+
+/// A source of glyphs for `SpriteBatch::draw_text`. Every size is multiplied by `scale`, which fonts with
+/// outlines rasterize at instead of magnifying their glyphs.
+pub trait Font {
+    fn line_metrics(&self, scale: f32) -> LineMetrics;
+
+    /// The glyph of `character` at `scale`, or `None` if the font has none.
+    fn scaled_glyph(&self, character: char, scale: f32) -> Option<Glyph>;
+
+    /// The adjustment of the advance of `first` when it is followed by `second`, at `scale`.
+    fn scaled_kerning(&self, first: char, second: char, scale: f32) -> f32;
+
+    /// Called with a different `segment` whenever text starts being laid out for another batch segment, as
+    /// `SpriteBatch` does before every text it draws. Fonts that evict glyphs keep all the glyphs used during
+    /// a segment until the next one begins, since the whole segment is drawn at once when it ends.
+    fn begin_segment(&self, _segment: u64) { }
+
+    /// The pixels of glyphs created since the last call, which must be written before they are drawn.
+    fn take_writes(&self) -> Vec<RenderTargetWrite> {
+        Vec::new()
+    }
+
+    /// The first error that left glyphs out of the text laid out since the last call.
+    fn take_error(&self) -> Option<FontError> {
+        None
+    }
+
+    /// The glyph quads of `text`, whose first line starts with its top-left corner at `position`.
+    /// Lines are separated by line breaks, and characters without a glyph are skipped.
+    /// `TextLayout` also wraps, aligns and styles text.
+    fn draw_data(&self, text: &str, position: Vector2, color: Color, scale: f32) -> Vec<DrawData> {
+        TextLayout::new(self, text, &TextOptions { color, scale, ..Default::default() }).draw_data(position)
+    }
+}
+
+/// A font whose glyphs are pre-rendered into page images, described by an AngelCode BMFont file.
+#[derive(Clone, Debug, Default)]
+pub struct BitmapFont {
+    line_height: f32,
+    base: f32,
+    pages: Vec<Sprite>,
+    glyphs: HashMap<char, Glyph>,
+    kernings: HashMap<(char, char), f32>
+}
+
+impl BitmapFont {
+    /// Reads a text or binary `.fnt` file and loads its pages, found next to it, as watched sprite files.
+    pub fn load_file(path: impl AsRef<Path>, sprite_loader: &mut SpriteLoader) -> Result<Self, FontError> {
+        let path = path.as_ref();
+        let data = fs::read(path).map_err(FontError::Io)?;
+        let directory = path.parent().unwrap_or(Path::new(""));
+        Self::from_bytes(&data, |file| sprite_loader.load_sprite_file(directory.join(file)).map_err(FontError::Sprite))
+    }
+
+    /// Parses a text or binary `.fnt` file, calling `load_page` with the file name of every page to get its sprite.
+    pub fn from_bytes(data: &[u8], mut load_page: impl FnMut(&str) -> Result<Sprite, FontError>) -> Result<Self, FontError> {
+        let descriptor = Descriptor::parse(data)?;
+        let pages = descriptor.pages.iter().map(|file| load_page(file)).collect::<Result<Vec<_>, _>>()?;
+
+        let mut glyphs = HashMap::with_capacity(descriptor.chars.len());
+        for char_descriptor in &descriptor.chars {
+            let Some(character) = char::from_u32(char_descriptor.id) else {
+                continue;
+            };
+            let sprite = *pages.get(char_descriptor.page as usize)
+                .ok_or_else(|| FontError::Parse(format!("character {} is on missing page {}", char_descriptor.id, char_descriptor.page)))?;
+
+            let (width, height) = (char_descriptor.width as f32, char_descriptor.height as f32);
+            let page_height = sprite.dimensions().1 as f32;
+            glyphs.insert(
+                character,
+                Glyph {
+                    sprite,
+                    source: Rectangle::new(char_descriptor.x as f32, page_height - char_descriptor.y as f32 - height, width, height),
+                    offset: Vector2::new(
+                        char_descriptor.x_offset as f32,
+                        descriptor.base as f32 - char_descriptor.y_offset as f32 - height
+                    ),
+                    advance: char_descriptor.x_advance as f32,
+                    scale: 1f32
+                }
+            );
+        }
+
+        let kernings = descriptor.kernings.iter().filter_map(
+            |(first, second, amount)| Some(((char::from_u32(*first)?, char::from_u32(*second)?), *amount as f32))
+        ).collect();
+
+        Ok(Self { line_height: descriptor.line_height as f32, base: descriptor.base as f32, pages, glyphs, kernings })
+    }
+
+    /// The distance between the baselines of consecutive lines.
+    pub fn line_height(&self) -> f32 {
+        self.line_height
+    }
+
+    /// The distance from the top of a line to its baseline.
+    pub fn base(&self) -> f32 {
+        self.base
+    }
+
+    pub fn pages(&self) -> &[Sprite] {
+        &self.pages
+    }
+
+    pub fn glyph(&self, character: char) -> Option<&Glyph> {
+        self.glyphs.get(&character)
+    }
+
+    /// The adjustment of the advance of `first` when it is followed by `second`.
+    pub fn kerning(&self, first: char, second: char) -> f32 {
+        self.kernings.get(&(first, second)).copied().unwrap_or(0f32)
+    }
+}
+
+impl Font for BitmapFont {
+    fn line_metrics(&self, scale: f32) -> LineMetrics {
+        LineMetrics { line_height: self.line_height * scale, base: self.base * scale }
+    }
+
+    fn scaled_glyph(&self, character: char, scale: f32) -> Option<Glyph> {
+        self.glyph(character).map(
+            |glyph| Glyph { offset: glyph.offset * scale, advance: glyph.advance * scale, scale, ..*glyph }
+        )
+    }
+
+    fn scaled_kerning(&self, first: char, second: char, scale: f32) -> f32 {
+        self.kerning(first, second) * scale
+    }
+}
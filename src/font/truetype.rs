@@ -0,0 +1,215 @@
+use std::{cell::RefCell, collections::HashMap, fs, path::Path};
+
+use ab_glyph::{Font as _, FontVec, PxScale, ScaleFont as _, point};
+use image::{Rgba, RgbaImage};
+
+use crate::{
+    atlas::ShelfPacker,
+    math::{Rectangle, Vector2},
+    render_target::{RenderTarget, RenderTargetWrite},
+    sprite::SpriteLoader
+};
+use super::{Font, FontError, Glyph, LineMetrics};
+
+/// A TrueType or OpenType font, rasterized on demand into a glyph atlas render target.
+/// Glyphs are drawn at the size of the font times the scale they are drawn with, so text stays sharp at any size.
+/// When the atlas is full, the glyphs that were used the longest time ago are evicted, except those used during the
+/// current batch segment, as set by `Font::begin_segment`. It should be large enough for the glyphs drawn during a segment:
+/// glyphs that don't fit are left out, and `SpriteBatch::end` returns `FontError::AtlasFull`.
+pub struct TrueTypeFont {
+    font: FontVec,
+    size: f32,
+    atlas: RefCell<GlyphAtlas>
+}
+
+struct GlyphAtlas {
+    render_target: RenderTarget,
+    packer: ShelfPacker,
+    /// Keyed by the character and the bits of the pixel size it was rasterized at.
+    glyphs: HashMap<(char, u32), CachedGlyph>,
+    /// Advanced whenever a segment begins, to find the least recently used glyphs.
+    /// Glyphs used at the current time are drawn by the current segment and cannot be evicted.
+    clock: u64,
+    /// The segment passed to the last `begin_segment`.
+    segment: u64,
+    writes: Vec<RenderTargetWrite>,
+    /// The first character whose glyph did not fit since the last `take_error`.
+    overflow: Option<char>
+}
+
+#[derive(Clone, Copy)]
+struct CachedGlyph {
+    glyph: Glyph,
+    /// The space packed for the glyph, including its transparent border.
+    position: (u32, u32),
+    dimensions: (u32, u32),
+    last_used: u64
+}
+
+impl TrueTypeFont {
+    /// Reads a `.ttf` or `.otf` file, as described by `from_bytes`.
+    pub fn load_file(path: impl AsRef<Path>, size: f32, atlas_dimensions: (u32, u32), sprite_loader: &mut SpriteLoader) -> Result<Self, FontError> {
+        let data = fs::read(path).map_err(FontError::Io)?;
+        Self::from_bytes(data, size, atlas_dimensions, sprite_loader)
+    }
+
+    /// Parses a TrueType or OpenType font, drawn `size` pixels from its lowest descender to its highest ascender,
+    /// and creates a render target of `atlas_dimensions` for its glyphs.
+    pub fn from_bytes(data: Vec<u8>, size: f32, atlas_dimensions: (u32, u32), sprite_loader: &mut SpriteLoader) -> Result<Self, FontError> {
+        let font = FontVec::try_from_vec(data).map_err(|error| FontError::Parse(error.to_string()))?;
+        let atlas = GlyphAtlas {
+            render_target: sprite_loader.create_render_target(atlas_dimensions.0, atlas_dimensions.1),
+            packer: ShelfPacker::new(atlas_dimensions, 0),
+            glyphs: HashMap::new(),
+            clock: 0,
+            segment: 0,
+            writes: Vec::new(),
+            overflow: None
+        };
+
+        Ok(Self { font, size, atlas: RefCell::new(atlas) })
+    }
+
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+
+    /// The render target holding the rasterized glyphs.
+    pub fn render_target(&self) -> RenderTarget {
+        self.atlas.borrow().render_target
+    }
+
+    /// How many glyphs are currently rasterized into the atlas.
+    pub fn cached_glyphs(&self) -> usize {
+        self.atlas.borrow().glyphs.len()
+    }
+
+    /// Rasterizes the glyph of `character` at `pixel_size` into the atlas, evicting the least recently used glyphs
+    /// if it is full. Returns `None` if it does not fit even once every glyph outside of the current segment is evicted.
+    fn rasterize(&self, atlas: &mut GlyphAtlas, character: char, pixel_size: f32) -> Option<CachedGlyph> {
+        let scaled_font = self.font.as_scaled(PxScale::from(pixel_size));
+        let glyph_id = self.font.glyph_id(character);
+        let advance = scaled_font.h_advance(glyph_id);
+        let Some(outline) = self.font.outline_glyph(glyph_id.with_scale_and_position(pixel_size, point(0f32, 0f32))) else {
+            // Glyphs without an outline, like spaces, only move the pen.
+            let glyph = Glyph {
+                sprite: atlas.render_target.sprite(),
+                source: Rectangle::default(),
+                offset: Vector2::ZERO,
+                advance,
+                scale: 1f32
+            };
+            return Some(CachedGlyph { glyph, position: (0, 0), dimensions: (0, 0), last_used: atlas.clock });
+        };
+
+        // A transparent border keeps linear filtering from sampling neighbouring or evicted glyphs.
+        let bounds = outline.px_bounds();
+        let (width, height) = (bounds.width() as u32, bounds.height() as u32);
+        let dimensions = (width + 2, height + 2);
+        let position = match pack_first_layer(&mut atlas.packer, dimensions) {
+            Some(position) => position,
+            None => {
+                let mut evictable: Vec<((char, u32), CachedGlyph)> = atlas.glyphs.iter()
+                    .filter(|(_, cached)| cached.last_used < atlas.clock && cached.dimensions != (0, 0))
+                    .map(|(key, cached)| (*key, *cached))
+                    .collect();
+                evictable.sort_by_key(|(_, cached)| cached.last_used);
+
+                // Freed space is never merged, so first check that the glyph fits once all the evictable glyphs are gone,
+                // rather than emptying the cache for nothing.
+                let mut packer = atlas.packer.clone();
+                for (_, cached) in &evictable {
+                    packer.free(0, cached.position);
+                }
+                pack_first_layer(&mut packer, dimensions)?;
+
+                let mut evictable = evictable.into_iter();
+                loop {
+                    let (key, evicted) = evictable.next()?;
+                    atlas.glyphs.remove(&key);
+                    atlas.packer.free(0, evicted.position);
+                    if let Some(position) = pack_first_layer(&mut atlas.packer, dimensions) {
+                        break position;
+                    }
+                }
+            }
+        };
+
+        let mut image = RgbaImage::new(dimensions.0, dimensions.1);
+        outline.draw(
+            |x, y, coverage| image.put_pixel(x + 1, y + 1, Rgba([255, 255, 255, (coverage.clamp(0f32, 1f32) * 255f32).round() as u8]))
+        );
+        atlas.writes.push(RenderTargetWrite { render_target: atlas.render_target, position, image });
+
+        let glyph = Glyph {
+            sprite: atlas.render_target.sprite(),
+            source: Rectangle::new(position.0 as f32 + 1f32, position.1 as f32 + 1f32, width as f32, height as f32),
+            offset: Vector2::new(bounds.min.x, -bounds.max.y),
+            advance,
+            scale: 1f32
+        };
+        Some(CachedGlyph { glyph, position, dimensions, last_used: atlas.clock })
+    }
+}
+
+impl Font for TrueTypeFont {
+    fn line_metrics(&self, scale: f32) -> LineMetrics {
+        let scaled_font = self.font.as_scaled(PxScale::from(self.size * scale));
+        LineMetrics { line_height: scaled_font.height() + scaled_font.line_gap(), base: scaled_font.ascent() }
+    }
+
+    /// Rasterizes the glyph into the atlas the first time it is drawn at this size.
+    fn scaled_glyph(&self, character: char, scale: f32) -> Option<Glyph> {
+        if self.font.glyph_id(character).0 == 0 {
+            return None;
+        }
+
+        let pixel_size = self.size * scale;
+        let mut atlas = self.atlas.borrow_mut();
+        let clock = atlas.clock;
+        let key = (character, pixel_size.to_bits());
+        if let Some(cached) = atlas.glyphs.get_mut(&key) {
+            cached.last_used = clock;
+            return Some(cached.glyph);
+        }
+
+        let Some(cached) = self.rasterize(&mut atlas, character, pixel_size) else {
+            atlas.overflow.get_or_insert(character);
+            return None;
+        };
+        atlas.glyphs.insert(key, cached);
+        Some(cached.glyph)
+    }
+
+    fn scaled_kerning(&self, first: char, second: char, scale: f32) -> f32 {
+        let scaled_font = self.font.as_scaled(PxScale::from(self.size * scale));
+        scaled_font.kern(self.font.glyph_id(first), self.font.glyph_id(second))
+    }
+
+    fn begin_segment(&self, segment: u64) {
+        let mut atlas = self.atlas.borrow_mut();
+        if atlas.segment != segment {
+            atlas.segment = segment;
+            atlas.clock += 1;
+        }
+    }
+
+    fn take_writes(&self) -> Vec<RenderTargetWrite> {
+        std::mem::take(&mut self.atlas.borrow_mut().writes)
+    }
+
+    fn take_error(&self) -> Option<FontError> {
+        self.atlas.borrow_mut().overflow.take().map(FontError::AtlasFull)
+    }
+}
+
+/// Packs a rectangle into the first layer, the only one backed by the atlas render target.
+fn pack_first_layer(packer: &mut ShelfPacker, dimensions: (u32, u32)) -> Option<(u32, u32)> {
+    match packer.pack(dimensions)? {
+        (0, position) => Some(position),
+        (layer, position) => {
+            packer.free(layer, position);
+            None
+        }
+    }
+}
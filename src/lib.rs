@@ -0,0 +1,46 @@
+pub mod math;
+mod sprite_batch;
+mod camera;
+mod mesh;
+mod renderer;
+mod render_target;
+mod application;
+mod sprite;
+mod atlas;
+mod color;
+mod effect;
+mod shape;
+mod nine_slice;
+mod font;
+mod sprite_sheet;
+mod texture_atlas;
+mod animation;
+
+pub use animation::{Animation, AnimationEvent, AnimationFrame, AnimationPlayer, LoopMode};
+pub use application::{run, ApplicationContext, HeadlessApplication};
+pub use camera::{Camera2D, VirtualResolution};
+pub use color::Color;
+pub use effect::{
+    Effect,
+    Fragment,
+    Shader,
+    ShaderSource,
+    SoftwareFragment,
+    UniformValue,
+    Uniforms,
+    DEFAULT_FRAGMENT_SHADER,
+    DEFAULT_VERTEX_SHADER
+};
+pub use font::{BitmapFont, Font, FontError, Glyph, LineMetrics, TextAlign, TextLayout, TextOptions, TrueTypeFont};
+pub use mesh::{Mesh, Vertex};
+pub use nine_slice::{Insets, NineSlice, SliceFill};
+pub use render_target::{RenderTarget, RenderTargetWrite};
+pub use shape::Shape;
+pub use renderer::{DrawCall, Renderer, GliumBackend, GliumRenderer, SoftwareRenderer};
+pub use sprite::{Sprite, SpriteError, SpriteLoader};
+pub use sprite_sheet::{Grid, SpriteSheet, TextureRegion};
+pub use texture_atlas::{AtlasError, Frame, FrameTag, TagDirection, TextureAtlas};
+pub use sprite_batch::{BlendMode, MaskMode, SpriteBatch, SpriteBatchError, SpriteSortMode, DrawData};
+
+pub use glium;
+pub use image;
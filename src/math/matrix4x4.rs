@@ -2,6 +2,7 @@ use std::ops::Mul;
 
 use super::Dot;
 
+#[derive(Clone, Copy, Debug)]
 pub struct Matrix4x4 {
     matrix: [[f32; 4]; 4]
 }
@@ -44,16 +45,51 @@ impl Matrix4x4 {
         )
     }
     
+    /// Shears x by `x` radians in proportion to y, and y by `y` radians in proportion to x.
+    pub fn new_skew(x: f32, y: f32) -> Self {
+        Self::new(
+            [
+                [1f32, x.tan(), 0f32, 0f32],
+                [y.tan(), 1f32, 0f32, 0f32],
+                [0f32, 0f32, 1f32, 0f32],
+                [0f32, 0f32, 0f32, 1f32]
+            ]
+        )
+    }
+
+    /// Builds a 2D affine transform from the rows of a 2x3 matrix, mapping `(x, y)` to
+    /// `(a * x + b * y + c, d * x + e * y + f)` for rows `[a, b, c]` and `[d, e, f]`.
+    pub fn new_affine(matrix: [[f32; 3]; 2]) -> Self {
+        let [[a, b, c], [d, e, f]] = matrix;
+        Self::new(
+            [
+                [a, b, 0f32, c],
+                [d, e, 0f32, f],
+                [0f32, 0f32, 1f32, 0f32],
+                [0f32, 0f32, 0f32, 1f32]
+            ]
+        )
+    }
+
+    pub fn transform(&self, vector: [f32; 4]) -> [f32; 4] {
+        let mut output = [0f32; 4];
+        for (element, row) in output.iter_mut().zip(self.matrix.iter()) {
+            *element = row.dot(&vector).unwrap();
+        }
+
+        output
+    }
+
     pub fn to_array(self) -> [[f32; 4]; 4] {
         self.matrix
     }
 
     fn column(&self, index: usize) -> Option<[f32; 4]> {
         Some([
-            self.matrix.get(0)?.get(index)?.clone(), 
-            self.matrix.get(1)?.get(index)?.clone(), 
-            self.matrix.get(2)?.get(index)?.clone(), 
-            self.matrix.get(3)?.get(index)?.clone()
+            *self.matrix.first()?.get(index)?, 
+            *self.matrix.get(1)?.get(index)?, 
+            *self.matrix.get(2)?.get(index)?, 
+            *self.matrix.get(3)?.get(index)?
         ])
     }
 }
@@ -63,9 +99,9 @@ impl Mul<Matrix4x4> for Matrix4x4 {
 
     fn mul(self, rhs: Matrix4x4) -> Self::Output {
         let mut matrix = [[0f32; 4]; 4];
-        for i in 0..4 {
-            for j in 0..4 {
-                matrix[i][j] = self.matrix[i].dot(&rhs.column(j).unwrap()).unwrap();
+        for (row, self_row) in matrix.iter_mut().zip(self.matrix.iter()) {
+            for (j, element) in row.iter_mut().enumerate() {
+                *element = self_row.dot(&rhs.column(j).unwrap()).unwrap();
             }
         }
 
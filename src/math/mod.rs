@@ -27,9 +27,8 @@ where
 {
     fn dot(self , rhs: Iterator2) -> Result<Output, DotError> {
         let mut output = Output::default();
-        let mut self_iter = self.into_iter();
         let mut rhs_iter = rhs.into_iter();
-        while let Some(x) = self_iter.next() {
+        for x in self {
             let Some(rhs_next) = rhs_iter.next() else {
                 return Err(DotError::DifferentElementCounts);
             };
@@ -40,6 +39,6 @@ where
             return Err(DotError::DifferentElementCounts);
         }
 
-        return Ok(output);
+        Ok(output)
     }
 }
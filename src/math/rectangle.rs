@@ -1,6 +1,6 @@
 use super::Vector2;
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct Rectangle {
     pub position: Vector2,
     pub width: f32,
@@ -36,4 +36,15 @@ impl Rectangle {
         rhs.position.x < self.right() && self.position.x < rhs.right() 
             && rhs.position.y < self.top() && self.position.y < rhs.top()
     }
+
+    /// The area covered by both rectangles, or `None` if they don't overlap.
+    pub fn intersection(&self, rhs: &Rectangle) -> Option<Rectangle> {
+        if !self.intersects(rhs) {
+            return None;
+        }
+
+        let left = self.position.x.max(rhs.position.x);
+        let bottom = self.position.y.max(rhs.position.y);
+        Some(Rectangle::new(left, bottom, self.right().min(rhs.right()) - left, self.top().min(rhs.top()) - bottom))
+    }
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
 use std::ops::{Mul, Add, MulAssign, AddAssign, Sub, SubAssign, DivAssign, Div};
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct Vector2 {
     pub x: f32,
     pub y: f32
@@ -17,7 +17,7 @@ impl Vector2 {
     }
 
     pub fn rotated_by(&self, origin: Vector2, rotation: f32) -> Vector2 {
-        let self_normalized = self.clone() - origin;
+        let self_normalized = *self - origin;
 
         Vector2::new(
             self_normalized.x * rotation.cos() + self_normalized.y * -rotation.sin(),
@@ -33,6 +33,34 @@ impl Vector2 {
         self.distance_squared(rhs).sqrt()
     }
 
+    pub fn length(&self) -> f32 {
+        self.distance(&Vector2::ZERO)
+    }
+
+    /// The vector scaled to a length of 1, or zero if it has no length.
+    pub fn normalized(&self) -> Vector2 {
+        let length = self.length();
+        if length == 0f32 {
+            return Vector2::ZERO;
+        }
+
+        *self * (1f32 / length)
+    }
+
+    /// The vector rotated a quarter turn counterclockwise.
+    pub fn perpendicular(&self) -> Vector2 {
+        Vector2::new(-self.y, self.x)
+    }
+
+    pub fn dot(&self, rhs: &Vector2) -> f32 {
+        self.x * rhs.x + self.y * rhs.y
+    }
+
+    /// The z component of the 3D cross product, positive if `rhs` is counterclockwise from `self`.
+    pub fn cross(&self, rhs: &Vector2) -> f32 {
+        self.x * rhs.y - self.y * rhs.x
+    }
+
     pub fn as_array(&self) -> [f32; 2] {
         [self.x, self.y]
     }
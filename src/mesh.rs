@@ -0,0 +1,124 @@
+use crate::{color::Color, math::{Matrix4x4, Vector2, Rectangle}, shape::Shape, sprite_batch::DrawData};
+
+/// A sprite batch vertex, already transformed into clip space.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vertex {
+    pub index: u32,
+    pub position: [f32; 3],
+    pub uv: [f32; 2],
+    pub color: [f32; 4]
+}
+
+/// The renderer-agnostic geometry of a batch: four vertices and six indices per sprite, followed by the triangles of each shape.
+#[derive(Clone, Debug, Default)]
+pub struct Mesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>
+}
+
+impl Mesh {
+    pub const VERTICES_PER_SPRITE: usize = 4;
+    pub const INDICES_PER_SPRITE: usize = 6;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds the quads of `draw_data` in order. `projection` maps world space to clip space and
+    /// `texture_dimensions` is the size of a texture array layer, used to normalize the sprite and `source` rectangles.
+    pub fn from_draw_data(draw_data: &[DrawData], projection: Matrix4x4, texture_dimensions: (u32, u32)) -> Self {
+        let mut mesh = Self {
+            vertices: Vec::with_capacity(draw_data.len() * Self::VERTICES_PER_SPRITE),
+            indices: Vec::with_capacity(draw_data.len() * Self::INDICES_PER_SPRITE)
+        };
+
+        for draw_data in draw_data {
+            mesh.push_sprite(draw_data, projection, texture_dimensions);
+        }
+
+        mesh
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub fn push_sprite(&mut self, draw_data: &DrawData, projection: Matrix4x4, texture_dimensions: (u32, u32)) {
+        let index = draw_data.sprite.index();
+        let max_sprite_size = Vector2::new(texture_dimensions.0 as f32, texture_dimensions.1 as f32);
+        let sprite_size = Vector2::new(draw_data.sprite.dimensions().0 as f32, draw_data.sprite.dimensions().1 as f32);
+        let source = draw_data.source.unwrap_or(Rectangle::new(0f32, 0f32, sprite_size.x, sprite_size.y));
+
+        let matrix = projection * local_to_world(draw_data) * Matrix4x4::new_scaling(source.width, source.height, 1f32);
+
+        let sprite_position = Vector2::new(draw_data.sprite.position().0 as f32, draw_data.sprite.position().1 as f32);
+        let mut texture_coordinates_min = (sprite_position + source.position) / max_sprite_size;
+        let mut texture_coordinates_max = texture_coordinates_min + source.size() / max_sprite_size;
+        if draw_data.flip_x {
+            std::mem::swap(&mut texture_coordinates_min.x, &mut texture_coordinates_max.x);
+        }
+        if draw_data.flip_y {
+            std::mem::swap(&mut texture_coordinates_min.y, &mut texture_coordinates_max.y);
+        }
+        let white = Color::new(1f32, 1f32, 1f32, 1f32);
+        let corner_colors = draw_data.corner_colors.unwrap_or([white; 4]).map(
+            |corner_color| [
+                draw_data.color.red * corner_color.red,
+                draw_data.color.green * corner_color.green,
+                draw_data.color.blue * corner_color.blue,
+                draw_data.color.alpha * corner_color.alpha
+            ]
+        );
+
+        let first_vertex = self.vertices.len() as u32;
+        for ((corner, uv), color) in [
+            ([0f32, 0f32], [texture_coordinates_min.x, texture_coordinates_min.y]),
+            ([1f32, 0f32], [texture_coordinates_max.x, texture_coordinates_min.y]),
+            ([1f32, 1f32], [texture_coordinates_max.x, texture_coordinates_max.y]),
+            ([0f32, 1f32], [texture_coordinates_min.x, texture_coordinates_max.y])
+        ].into_iter().zip(corner_colors) {
+            let [x, y, z, _] = matrix.transform([corner[0], corner[1], draw_data.depth, 1f32]);
+            self.vertices.push(Vertex { index, position: [x, y, z], uv, color });
+        }
+
+        self.indices.extend_from_slice(&[
+            first_vertex,
+            first_vertex + 1,
+            first_vertex + 2,
+            first_vertex + 2,
+            first_vertex + 3,
+            first_vertex
+        ]);
+    }
+
+    /// Adds the triangles of `shape`, whose points are transformed by `draw_data` like the corners of a sprite,
+    /// except that `origin` is in the units of the points. Every vertex samples the centre of the source rectangle
+    /// of `draw_data.sprite`, by default the white pixel, tinted by `color`.
+    pub fn push_shape(&mut self, shape: &Shape, draw_data: &DrawData, projection: Matrix4x4, texture_dimensions: (u32, u32)) {
+        let index = draw_data.sprite.index();
+        let max_sprite_size = Vector2::new(texture_dimensions.0 as f32, texture_dimensions.1 as f32);
+        let sprite_size = Vector2::new(draw_data.sprite.dimensions().0 as f32, draw_data.sprite.dimensions().1 as f32);
+        let source = draw_data.source.unwrap_or(Rectangle::new(0f32, 0f32, sprite_size.x, sprite_size.y));
+        let sprite_position = Vector2::new(draw_data.sprite.position().0 as f32, draw_data.sprite.position().1 as f32);
+        let uv = ((sprite_position + source.center()) / max_sprite_size).as_array();
+        let color = [draw_data.color.red, draw_data.color.green, draw_data.color.blue, draw_data.color.alpha];
+
+        let matrix = projection * local_to_world(draw_data);
+        let first_vertex = self.vertices.len() as u32;
+        for point in &shape.points {
+            let [x, y, z, _] = matrix.transform([point.x, point.y, draw_data.depth, 1f32]);
+            self.vertices.push(Vertex { index, position: [x, y, z], uv, color });
+        }
+
+        self.indices.extend(shape.indices.iter().map(|index| first_vertex + index));
+    }
+}
+
+/// Places the origin at the position, after scaling, transforming and rotating around it.
+fn local_to_world(draw_data: &DrawData) -> Matrix4x4 {
+    Matrix4x4::new_translation(draw_data.position.x, draw_data.position.y, 0f32)
+        * Matrix4x4::new_rotation(draw_data.rotation)
+        * draw_data.transform.unwrap_or(Matrix4x4::new_scaling(1f32, 1f32, 1f32))
+        * Matrix4x4::new_scaling(draw_data.scale.x, draw_data.scale.y, 1f32)
+        * Matrix4x4::new_translation(-draw_data.origin.x, -draw_data.origin.y, 0f32)
+}
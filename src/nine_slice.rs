@@ -0,0 +1,133 @@
+use defaults::Defaults;
+
+use crate::{color::Color, math::{Rectangle, Vector2}, sprite::Sprite, sprite_batch::DrawData};
+
+/// Distances from the edges of a sprite to its stretchable centre, in pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Insets {
+    pub left: f32,
+    pub bottom: f32,
+    pub right: f32,
+    pub top: f32
+}
+
+impl Insets {
+    pub fn new(left: f32, bottom: f32, right: f32, top: f32) -> Self {
+        Self { left, bottom, right, top }
+    }
+
+    /// The same inset on every side.
+    pub fn uniform(inset: f32) -> Self {
+        Self::new(inset, inset, inset, inset)
+    }
+}
+
+/// How the edges and centre of a nine-slice sprite cover their part of the destination.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SliceFill {
+    #[default]
+    Stretch,
+    /// Repeats the slice at its original size, cutting off the last repetition at the end of the destination.
+    Tile
+}
+
+/// A sprite split by its `insets` into nine slices, so it can cover `destination` without distorting its borders.
+/// The corners keep their size, the edges are stretched or tiled along their length, and so is the centre.
+/// If the destination is smaller than the corners, they are shrunk to fit.
+#[derive(Defaults)]
+pub struct NineSlice {
+    pub sprite: Sprite,
+    /// The part of the sprite that is sliced, by default all of it.
+    pub source: Option<Rectangle>,
+    pub insets: Insets,
+    pub destination: Rectangle,
+    pub fill: SliceFill,
+    #[def = "Color::new(1f32, 1f32, 1f32, 1f32)"]
+    pub color: Color,
+    pub depth: f32
+}
+
+impl NineSlice {
+    /// The quads covering the destination, from the bottom-left corner row by row.
+    pub fn draw_data(&self) -> Vec<DrawData> {
+        let (width, height) = self.sprite.dimensions();
+        let source = self.source.unwrap_or(Rectangle::new(0f32, 0f32, width as f32, height as f32));
+        let columns = slices(
+            (self.destination.position.x, self.destination.width),
+            (source.position.x, source.width),
+            (self.insets.left, self.insets.right),
+            self.fill
+        );
+        let rows = slices(
+            (self.destination.position.y, self.destination.height),
+            (source.position.y, source.height),
+            (self.insets.bottom, self.insets.top),
+            self.fill
+        );
+
+        rows.iter().flat_map(
+            |row| columns.iter().map(
+                move |column| DrawData {
+                    sprite: self.sprite,
+                    position: Vector2::new(column.destination, row.destination),
+                    source: Some(Rectangle::new(column.source, row.source, column.source_length, row.source_length)),
+                    scale: Vector2::new(column.length / column.source_length, row.length / row.source_length),
+                    color: self.color,
+                    depth: self.depth,
+                    ..Default::default()
+                }
+            )
+        ).collect()
+    }
+}
+
+/// A span of the destination along one axis and the span of the source it shows.
+struct Slice {
+    destination: f32,
+    length: f32,
+    source: f32,
+    source_length: f32
+}
+
+/// Splits the `destination` span into the slices of the `source` span, leaving out empty ones.
+fn slices(destination: (f32, f32), source: (f32, f32), insets: (f32, f32), fill: SliceFill) -> Vec<Slice> {
+    let (start, length) = destination;
+    let (source_start, source_length) = source;
+    let (inset_start, inset_end) = (insets.0.min(source_length), insets.1.min(source_length - insets.0.min(source_length)));
+    let border_scale = (length / (inset_start + inset_end)).min(1f32);
+    let middle_source_length = source_length - inset_start - inset_end;
+    let middle_length = (length - (inset_start + inset_end) * border_scale).max(0f32);
+
+    let mut slices = vec![
+        Slice { destination: start, length: inset_start * border_scale, source: source_start, source_length: inset_start }
+    ];
+    let middle_start = start + inset_start * border_scale;
+    let middle_source_start = source_start + inset_start;
+    match fill {
+        SliceFill::Stretch => slices.push(
+            Slice { destination: middle_start, length: middle_length, source: middle_source_start, source_length: middle_source_length }
+        ),
+        SliceFill::Tile if middle_source_length > 0f32 => {
+            let mut offset = 0f32;
+            while offset < middle_length {
+                let tile_length = middle_source_length.min(middle_length - offset);
+                slices.push(
+                    Slice { destination: middle_start + offset, length: tile_length, source: middle_source_start, source_length: tile_length }
+                );
+                offset += middle_source_length;
+            }
+        },
+        SliceFill::Tile => ()
+    }
+    slices.push(
+        Slice {
+            destination: middle_start + middle_length,
+            length: inset_end * border_scale,
+            source: source_start + source_length - inset_end,
+            source_length: inset_end
+        }
+    );
+
+    slices.retain(|slice| slice.length > 0f32 && slice.source_length > 0f32);
+    slices
+}
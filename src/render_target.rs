@@ -0,0 +1,40 @@
+use image::RgbaImage;
+
+use crate::sprite::Sprite;
+
+/// An offscreen texture a `SpriteBatch` can draw into with `SpriteBatch::begin_target` and that can
+/// then be drawn like any other sprite, in the same frame. Created with `SpriteLoader::create_render_target`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RenderTarget {
+    id: u32,
+    dimensions: (u32, u32)
+}
+
+impl RenderTarget {
+    pub(crate) fn new(id: u32, dimensions: (u32, u32)) -> Self {
+        Self { id, dimensions }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.dimensions
+    }
+
+    /// A sprite covering the whole target. Drawing it into the target itself is undefined.
+    pub fn sprite(&self) -> Sprite {
+        Sprite::from_render_target(*self)
+    }
+}
+
+/// Pixels to copy into a render target before the next batch segment is drawn, as done by `Renderer::write_render_target`.
+#[derive(Clone, Debug)]
+pub struct RenderTargetWrite {
+    pub render_target: RenderTarget,
+    /// The bottom-left corner of the pixels in the target.
+    pub position: (u32, u32),
+    /// Stored top row first.
+    pub image: RgbaImage
+}
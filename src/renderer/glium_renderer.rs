@@ -0,0 +1,274 @@
+use glium::{
+    Program, 
+    implement_vertex, 
+    Display, 
+    glutin::surface::WindowSurface,
+    VertexBuffer, 
+    IndexBuffer, 
+    index::PrimitiveType, 
+    texture::{Texture2d, Texture2dArray, RawImage2d, StencilFormat, TextureCreationError}, 
+    framebuffer::{SimpleFrameBuffer, StencilRenderBuffer, RenderBufferCreationError},
+    BlitTarget,
+    Rect,
+    Surface, 
+    ProgramCreationError,
+    uniforms::{self, MagnifySamplerFilter, SamplerBehavior}
+};
+use image::RgbaImage;
+
+use crate::{
+    color::Color,
+    effect::{Effect, Shader, UniformValue, Uniforms},
+    mesh::{Mesh, Vertex},
+    render_target::RenderTarget,
+    sprite::{Sprite, SpriteLoader},
+    sprite_batch::SpriteBatchError
+};
+use super::{DrawCall, Renderer};
+
+implement_vertex!(Vertex, index, position, uv, color);
+
+/// The GPU resources shared by every frame drawn with glium.
+pub struct GliumBackend {
+    pub program: Program,
+    display: Display<WindowSurface>,
+    texture_array: Texture2dArray,
+    /// The `SpriteLoader::version` the texture array was last updated to.
+    texture_version: u64,
+    render_targets: Vec<GliumRenderTarget>,
+    shaders: Vec<(Shader, Program)>
+}
+
+struct GliumRenderTarget {
+    render_target: RenderTarget,
+    /// A single layer array, so the default shader can sample it like the sprites.
+    texture: Texture2dArray,
+    stencil: StencilRenderBuffer
+}
+
+impl GliumBackend {
+    pub fn new(display: Display<WindowSurface>, program: Program, texture_array: Texture2dArray) -> Self {
+        Self { program, display, texture_array, texture_version: 0, render_targets: Vec::new(), shaders: Vec::new() }
+    }
+
+    pub fn display(&self) -> &Display<WindowSurface> {
+        &self.display
+    }
+
+    pub fn texture_array(&self) -> &Texture2dArray {
+        &self.texture_array
+    }
+
+    /// Rebuilds the texture array from the sprites of `sprite_loader`, or only uploads the replaced sprites if none were loaded or unloaded
+    /// since the last call, and creates or frees the textures of its render targets. Render targets that already existed keep their contents.
+    /// If the texture array cannot be created, the previous one is kept.
+    pub fn update_textures(&mut self, sprite_loader: &SpriteLoader) -> Result<(), TextureCreationError> {
+        let uploaded = sprite_loader.replaced_since(self.texture_version).is_some_and(
+            |replaced| replaced.into_iter().all(|(sprite, image)| self.write_sprite(sprite, image).is_ok())
+        );
+        if uploaded {
+            // Blitting only fills the main level.
+            unsafe { self.texture_array.generate_mipmaps() };
+        } else {
+            self.texture_array = sprite_loader.create_texture_array(&self.display)?;
+        }
+        self.texture_version = sprite_loader.version();
+
+        self.render_targets.retain(|created| sprite_loader.render_targets().contains(&created.render_target));
+        for render_target in sprite_loader.render_targets() {
+            if self.render_targets.iter().any(|created| created.render_target == *render_target) {
+                continue;
+            }
+
+            let (width, height) = render_target.dimensions();
+            let texture = Texture2dArray::new(
+                &self.display,
+                vec![RawImage2d::from_raw_rgba(vec![0u8; (width * height * 4) as usize], (width, height))]
+            )?;
+            let stencil = StencilRenderBuffer::new(&self.display, StencilFormat::I8, width, height).map_err(
+                |error| match error {
+                    RenderBufferCreationError::FormatNotSupported => TextureCreationError::FormatNotSupported
+                }
+            )?;
+            self.render_targets.push(GliumRenderTarget { render_target: *render_target, texture, stencil });
+        }
+
+        Ok(())
+    }
+
+    /// Copies the image of a replaced sprite into its place in the texture array.
+    fn write_sprite(&self, sprite: Sprite, image: &RgbaImage) -> Result<(), TextureCreationError> {
+        let (width, height) = image.dimensions();
+        let source = Texture2d::new(&self.display, RawImage2d::from_raw_rgba_reversed(image.as_raw(), (width, height)))?;
+        let layer = self.texture_array.layer(sprite.index()).ok_or(TextureCreationError::DimensionsNotSupported)?;
+        let framebuffer = SimpleFrameBuffer::new(&self.display, layer.main_level())
+            .map_err(|_| TextureCreationError::FormatNotSupported)?;
+        let (x, y) = sprite.position();
+        source.as_surface().blit_color(
+            &Rect { left: 0, bottom: 0, width, height },
+            &framebuffer,
+            &BlitTarget { left: x, bottom: y, width: width as i32, height: height as i32 },
+            MagnifySamplerFilter::Nearest
+        );
+        Ok(())
+    }
+
+    /// Compiles the shaders loaded by `sprite_loader` since the last call and frees the unloaded ones.
+    /// Returns the shaders that failed to compile, which are left out while the others are still compiled.
+    pub fn update_shaders(&mut self, sprite_loader: &SpriteLoader) -> Vec<(Shader, ProgramCreationError)> {
+        self.shaders.retain(|(shader, _)| sprite_loader.shaders().iter().any(|(loaded, _)| loaded == shader));
+        let mut errors = Vec::new();
+        for (shader, source) in sprite_loader.shaders() {
+            if self.shaders.iter().any(|(compiled, _)| compiled == shader) {
+                continue;
+            }
+
+            match Program::from_source(&self.display, &source.vertex, &source.fragment, None) {
+                Ok(program) => self.shaders.push((*shader, program)),
+                Err(error) => errors.push((*shader, error))
+            }
+        }
+
+        errors
+    }
+
+    fn program(&self, effect: Option<&Effect>) -> Result<&Program, SpriteBatchError> {
+        let Some(effect) = effect else {
+            return Ok(&self.program);
+        };
+
+        self.shaders.iter()
+            .find(|(compiled, _)| *compiled == effect.shader)
+            .map(|(_, program)| program)
+            .ok_or(SpriteBatchError::UnknownShader)
+    }
+
+    fn render_target(&self, render_target: RenderTarget) -> Result<&GliumRenderTarget, SpriteBatchError> {
+        self.render_targets.iter()
+            .find(|created| created.render_target == render_target)
+            .ok_or(SpriteBatchError::UnknownRenderTarget)
+    }
+
+    fn render_target_framebuffer(&self, render_target: RenderTarget) -> Result<SimpleFrameBuffer<'_>, SpriteBatchError> {
+        let render_target = self.render_target(render_target)?;
+        SimpleFrameBuffer::with_stencil_buffer(&self.display, render_target.texture.layer(0).unwrap().main_level(), &render_target.stencil)
+            .map_err(SpriteBatchError::FramebufferCreation)
+    }
+
+    /// Creates a renderer that draws onto `surface`, usually the `Frame` of the current draw.
+    pub fn renderer<'a, S: Surface>(&'a self, surface: &'a mut S) -> GliumRenderer<'a, S> {
+        GliumRenderer { backend: self, surface }
+    }
+}
+
+pub struct GliumRenderer<'a, S: Surface> {
+    backend: &'a GliumBackend,
+    surface: &'a mut S
+}
+
+impl <'a, S: Surface> Renderer for GliumRenderer<'a, S> {
+    fn dimensions(&self) -> (u32, u32) {
+        self.surface.get_dimensions()
+    }
+
+    fn texture_dimensions(&self) -> (u32, u32) {
+        (self.backend.texture_array.width(), self.backend.texture_array.height())
+    }
+
+    fn draw(&mut self, mesh: &Mesh, draw_call: &DrawCall) -> Result<(), SpriteBatchError> {
+        let display = &self.backend.display;
+        let vertex_buffer = VertexBuffer::new(display, &mesh.vertices)
+            .map_err(SpriteBatchError::VertexBufferCreation)?;
+        let index_buffer = IndexBuffer::new(display, PrimitiveType::TrianglesList, &mesh.indices)
+            .map_err(SpriteBatchError::IndexBufferCreation)?;
+
+        let texture = match draw_call.texture {
+            Some(texture) => &self.backend.render_target(texture)?.texture,
+            None => &self.backend.texture_array
+        };
+        let program = self.backend.program(draw_call.effect)?;
+        let target_dimensions = draw_call.target.map_or(self.surface.get_dimensions(), |target| target.dimensions());
+        let resolution = draw_call.draw_parameters.viewport.map_or(target_dimensions, |viewport| (viewport.width, viewport.height));
+        let uniforms = EffectUniforms {
+            texture,
+            sampler_behaviour: draw_call.sampler_behaviour,
+            resolution: [resolution.0 as f32, resolution.1 as f32],
+            alpha_cutoff: draw_call.alpha_cutoff,
+            uniforms: draw_call.effect.map(|effect| &effect.uniforms)
+        };
+
+        match draw_call.target {
+            Some(target) => self.backend.render_target_framebuffer(target)?.draw(
+                &vertex_buffer,
+                &index_buffer,
+                program,
+                &uniforms,
+                &draw_call.draw_parameters
+            ),
+            None => self.surface.draw(
+                &vertex_buffer,
+                &index_buffer,
+                program,
+                &uniforms,
+                &draw_call.draw_parameters
+            )
+        }.map_err(SpriteBatchError::Draw)
+    }
+
+    fn clear_render_target(&mut self, target: RenderTarget, color: Color) -> Result<(), SpriteBatchError> {
+        self.backend.render_target_framebuffer(target)?.clear_color(color.red, color.green, color.blue, color.alpha);
+        Ok(())
+    }
+
+    fn write_render_target(&mut self, target: RenderTarget, position: (u32, u32), image: &RgbaImage) -> Result<(), SpriteBatchError> {
+        let (width, height) = image.dimensions();
+        let source = Texture2d::new(&self.backend.display, RawImage2d::from_raw_rgba_reversed(image.as_raw(), (width, height)))
+            .map_err(SpriteBatchError::TextureCreation)?;
+        source.as_surface().blit_color(
+            &Rect { left: 0, bottom: 0, width, height },
+            &self.backend.render_target_framebuffer(target)?,
+            &BlitTarget { left: position.0, bottom: position.1, width: width as i32, height: height as i32 },
+            MagnifySamplerFilter::Nearest
+        );
+        Ok(())
+    }
+
+    fn clear_mask(&mut self, target: Option<RenderTarget>) -> Result<(), SpriteBatchError> {
+        match target {
+            Some(target) => self.backend.render_target_framebuffer(target)?.clear_stencil(0),
+            None => self.surface.clear_stencil(0)
+        }
+
+        Ok(())
+    }
+}
+
+/// The uniforms every shader receives, followed by those of its effect.
+struct EffectUniforms<'a> {
+    texture: &'a Texture2dArray,
+    sampler_behaviour: SamplerBehavior,
+    resolution: [f32; 2],
+    alpha_cutoff: f32,
+    uniforms: Option<&'a Uniforms>
+}
+
+impl uniforms::Uniforms for EffectUniforms<'_> {
+    fn visit_values<'b, F: FnMut(&str, uniforms::UniformValue<'b>)>(&'b self, mut visit: F) {
+        visit("textures", uniforms::UniformValue::Texture2dArray(self.texture, Some(self.sampler_behaviour)));
+        visit("resolution", uniforms::UniformValue::Vec2(self.resolution));
+        visit("alpha_cutoff", uniforms::UniformValue::Float(self.alpha_cutoff));
+        for (name, value) in self.uniforms.iter().flat_map(|uniforms| uniforms.iter()) {
+            let value = match value {
+                UniformValue::Float(value) => uniforms::UniformValue::Float(value),
+                UniformValue::Vec2(value) => uniforms::UniformValue::Vec2(value),
+                UniformValue::Vec3(value) => uniforms::UniformValue::Vec3(value),
+                UniformValue::Vec4(value) => uniforms::UniformValue::Vec4(value),
+                UniformValue::Int(value) => uniforms::UniformValue::SignedInt(value),
+                UniformValue::UnsignedInt(value) => uniforms::UniformValue::UnsignedInt(value),
+                UniformValue::Bool(value) => uniforms::UniformValue::Bool(value),
+                UniformValue::Matrix4(value) => uniforms::UniformValue::Mat4(value)
+            };
+            visit(name, value);
+        }
+    }
+}
@@ -0,0 +1,47 @@
+mod glium_renderer;
+mod software_renderer;
+
+pub use glium_renderer::{GliumBackend, GliumRenderer};
+pub use software_renderer::SoftwareRenderer;
+
+use glium::{DrawParameters, uniforms::SamplerBehavior};
+use image::RgbaImage;
+
+use crate::{color::Color, effect::Effect, mesh::Mesh, render_target::RenderTarget, sprite_batch::SpriteBatchError};
+
+/// The state a mesh is drawn with.
+#[derive(Clone, Debug)]
+pub struct DrawCall<'a> {
+    /// The render target to sample instead of the texture array.
+    pub texture: Option<RenderTarget>,
+    /// The render target to draw onto instead of the surface.
+    pub target: Option<RenderTarget>,
+    /// Shades the mesh instead of the default shader.
+    pub effect: Option<&'a Effect>,
+    /// Fragments with a lower alpha are discarded, leaving the stencil buffer untouched.
+    /// Passed to shaders as the `alpha_cutoff` uniform.
+    pub alpha_cutoff: f32,
+    pub draw_parameters: DrawParameters<'a>,
+    pub sampler_behaviour: SamplerBehavior
+}
+
+/// Something a `SpriteBatch` can submit its meshes to.
+pub trait Renderer {
+    /// The size of the surface being drawn onto, in pixels.
+    fn dimensions(&self) -> (u32, u32);
+
+    /// The size of a single texture array layer, in pixels.
+    fn texture_dimensions(&self) -> (u32, u32);
+
+    /// Draws the triangles of `mesh` with a single draw call.
+    fn draw(&mut self, mesh: &Mesh, draw_call: &DrawCall) -> Result<(), SpriteBatchError>;
+
+    /// Fills the whole render target with `color`, ignoring blending.
+    fn clear_render_target(&mut self, target: RenderTarget, color: Color) -> Result<(), SpriteBatchError>;
+
+    /// Replaces the pixels of `target` from `position`, their bottom-left corner, with `image`, which is stored top row first.
+    fn write_render_target(&mut self, target: RenderTarget, position: (u32, u32), image: &RgbaImage) -> Result<(), SpriteBatchError>;
+
+    /// Resets the stencil buffer of `target`, or of the surface if it is `None`, removing every mask written into it.
+    fn clear_mask(&mut self, target: Option<RenderTarget>) -> Result<(), SpriteBatchError>;
+}
@@ -0,0 +1,552 @@
+use glium::{
+    BlendingFunction,
+    DrawParameters,
+    LinearBlendingFactor,
+    Rect,
+    StencilOperation,
+    StencilTest,
+    uniforms::{MagnifySamplerFilter, MinifySamplerFilter, SamplerWrapFunction}
+};
+use image::{Rgba, RgbaImage, imageops};
+
+use crate::{
+    color::Color,
+    effect::{Fragment, Shader, SoftwareFragment, Uniforms},
+    math::Vector2,
+    mesh::{Mesh, Vertex},
+    render_target::RenderTarget,
+    sprite::{self, SpriteLoader},
+    sprite_batch::SpriteBatchError
+};
+use super::{DrawCall, Renderer};
+
+/// A CPU rasterizer that draws onto an `RgbaImage`, for rendering without a GPU.
+/// It emulates the default shader, honouring the viewport, scissor and blending of the `DrawParameters` and the
+/// filtering and wrapping of the `SamplerBehavior`. The surface and every render target have an 8 bit stencil buffer,
+/// used by the stencil test and colour mask of the `DrawParameters`. Depth testing and mipmaps are not supported.
+/// Effects run the `SoftwareFragment` of their shader instead of its GLSL source.
+pub struct SoftwareRenderer {
+    layers: Vec<RgbaImage>,
+    /// The `SpriteLoader::version` the layers were last updated to.
+    texture_version: u64,
+    render_targets: Vec<(RenderTarget, Canvas)>,
+    shaders: Vec<(Shader, Option<SoftwareFragment>)>,
+    canvas: Canvas
+}
+
+/// An image and its stencil buffer, stored in the same order.
+#[derive(Clone, Default)]
+struct Canvas {
+    image: RgbaImage,
+    stencil: Vec<u8>
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32) -> Self {
+        Self { image: RgbaImage::new(width, height), stencil: vec![0u8; (width * height) as usize] }
+    }
+}
+
+impl SoftwareRenderer {
+    /// `layers` are the texture array layers, as created by `SpriteLoader::create_layers`.
+    pub fn new(layers: Vec<RgbaImage>, width: u32, height: u32) -> Self {
+        Self { layers, texture_version: 0, render_targets: Vec::new(), shaders: Vec::new(), canvas: Canvas::new(width, height) }
+    }
+
+    /// Replaces the layers, which `update_textures` creates again from its `SpriteLoader` the next time.
+    pub fn set_layers(&mut self, layers: Vec<RgbaImage>) {
+        self.layers = layers;
+        self.texture_version = 0;
+    }
+
+    /// Rebuilds the layers from the sprites of `sprite_loader`, or only copies the replaced sprites if none were loaded or unloaded
+    /// since the last call, and creates or frees the images of its render targets. Render targets that already existed keep their contents.
+    pub fn update_textures(&mut self, sprite_loader: &SpriteLoader) {
+        match sprite_loader.replaced_since(self.texture_version) {
+            Some(replaced) => {
+                for (sprite, image) in replaced {
+                    sprite::copy_to_layer(&mut self.layers[sprite.index() as usize], sprite, image);
+                }
+            },
+            None => self.layers = sprite_loader.create_layers()
+        }
+        self.texture_version = sprite_loader.version();
+        self.render_targets.retain(|(render_target, _)| sprite_loader.render_targets().contains(render_target));
+        for render_target in sprite_loader.render_targets() {
+            if !self.render_targets.iter().any(|(created, _)| created == render_target) {
+                let (width, height) = render_target.dimensions();
+                self.render_targets.push((*render_target, Canvas::new(width, height)));
+            }
+        }
+    }
+
+    /// Takes the software fragments of the shaders loaded by `sprite_loader`.
+    pub fn update_shaders(&mut self, sprite_loader: &SpriteLoader) {
+        self.shaders = sprite_loader.shaders().iter().map(|(shader, source)| (*shader, source.software_fragment)).collect();
+    }
+
+    pub fn image(&self) -> &RgbaImage {
+        &self.canvas.image
+    }
+
+    /// The contents of a render target, top row first like `image`.
+    pub fn render_target_image(&self, render_target: RenderTarget) -> Option<&RgbaImage> {
+        self.render_targets.iter().find(|(created, _)| *created == render_target).map(|(_, canvas)| &canvas.image)
+    }
+
+    pub fn into_image(self) -> RgbaImage {
+        self.canvas.image
+    }
+
+    /// Fills the image with `color` and resets its stencil buffer.
+    pub fn clear(&mut self, color: Color) {
+        fill(&mut self.canvas.image, color);
+        self.canvas.stencil.fill(0);
+    }
+
+    fn render_target_index(&self, render_target: RenderTarget) -> Result<usize, SpriteBatchError> {
+        self.render_targets.iter()
+            .position(|(created, _)| *created == render_target)
+            .ok_or(SpriteBatchError::UnknownRenderTarget)
+    }
+}
+
+impl Renderer for SoftwareRenderer {
+    fn dimensions(&self) -> (u32, u32) {
+        self.canvas.image.dimensions()
+    }
+
+    fn texture_dimensions(&self) -> (u32, u32) {
+        self.layers.first().map_or((1, 1), RgbaImage::dimensions)
+    }
+
+    fn draw(&mut self, mesh: &Mesh, draw_call: &DrawCall) -> Result<(), SpriteBatchError> {
+        let texture_index = draw_call.texture.map(|texture| self.render_target_index(texture)).transpose()?;
+        let target_index = draw_call.target.map(|target| self.render_target_index(target)).transpose()?;
+        let shading = match draw_call.effect {
+            Some(effect) => {
+                let (_, software_fragment) = self.shaders.iter()
+                    .find(|(loaded, _)| *loaded == effect.shader)
+                    .ok_or(SpriteBatchError::UnknownShader)?;
+                software_fragment.map(|software_fragment| (software_fragment, &effect.uniforms))
+            },
+            None => None
+        };
+        if texture_index.is_none() && self.layers.is_empty() {
+            return Ok(());
+        }
+
+        let mut destination = match target_index {
+            Some(index) => std::mem::take(&mut self.render_targets[index].1),
+            None => std::mem::take(&mut self.canvas)
+        };
+
+        // A render target drawn into itself samples its contents from before the draw.
+        let snapshot;
+        let layers = match texture_index {
+            Some(_) if texture_index == target_index => {
+                snapshot = destination.image.clone();
+                std::slice::from_ref(&snapshot)
+            },
+            Some(index) => std::slice::from_ref(&self.render_targets[index].1.image),
+            None => &self.layers[..]
+        };
+
+        for triangle in mesh.indices.chunks_exact(3) {
+            let vertices = [
+                mesh.vertices[triangle[0] as usize],
+                mesh.vertices[triangle[1] as usize],
+                mesh.vertices[triangle[2] as usize]
+            ];
+            draw_triangle(&mut destination, layers, vertices, shading, draw_call);
+        }
+
+        match target_index {
+            Some(index) => self.render_targets[index].1 = destination,
+            None => self.canvas = destination
+        }
+
+        Ok(())
+    }
+
+    fn clear_render_target(&mut self, target: RenderTarget, color: Color) -> Result<(), SpriteBatchError> {
+        let index = self.render_target_index(target)?;
+        fill(&mut self.render_targets[index].1.image, color);
+        Ok(())
+    }
+
+    fn write_render_target(&mut self, target: RenderTarget, position: (u32, u32), image: &RgbaImage) -> Result<(), SpriteBatchError> {
+        let index = self.render_target_index(target)?;
+        let (_, height) = target.dimensions();
+        imageops::replace(
+            &mut self.render_targets[index].1.image,
+            image,
+            position.0 as i64,
+            height as i64 - position.1 as i64 - image.height() as i64
+        );
+        Ok(())
+    }
+
+    fn clear_mask(&mut self, target: Option<RenderTarget>) -> Result<(), SpriteBatchError> {
+        let canvas = match target {
+            Some(target) => {
+                let index = self.render_target_index(target)?;
+                &mut self.render_targets[index].1
+            },
+            None => &mut self.canvas
+        };
+
+        canvas.stencil.fill(0);
+        Ok(())
+    }
+}
+
+fn fill(image: &mut RgbaImage, color: Color) {
+    let pixel = Rgba([color.red, color.green, color.blue, color.alpha].map(to_u8));
+    for destination in image.pixels_mut() {
+        *destination = pixel;
+    }
+}
+
+fn draw_triangle(
+    canvas: &mut Canvas,
+    layers: &[RgbaImage],
+    mut vertices: [Vertex; 3],
+    shading: Option<(SoftwareFragment, &Uniforms)>,
+    draw_call: &DrawCall
+) {
+    let (draw_parameters, sampler_behaviour) = (&draw_call.draw_parameters, draw_call.sampler_behaviour);
+    let (width, height) = canvas.image.dimensions();
+    let viewport = draw_parameters.viewport.unwrap_or(Rect { left: 0, bottom: 0, width, height });
+    let viewport_top = height as f32 - (viewport.bottom + viewport.height) as f32;
+    let to_screen = |vertex: &Vertex| Vector2::new(
+        viewport.left as f32 + (vertex.position[0] + 1f32) * 0.5 * viewport.width as f32,
+        viewport_top + (1f32 - vertex.position[1]) * 0.5 * viewport.height as f32
+    );
+
+    let mut points = [to_screen(&vertices[0]), to_screen(&vertices[1]), to_screen(&vertices[2])];
+    let mut area = edge(points[0], points[1], points[2]);
+    if area == 0f32 {
+        return;
+    }
+
+    // Points are y down, so a triangle that is counterclockwise on screen has a negative area.
+    let stencil = &draw_parameters.stencil;
+    let (test, reference, write_mask, fail_operation, pass_operation) = if area < 0f32 {
+        (
+            stencil.test_counter_clockwise,
+            stencil.reference_value_counter_clockwise,
+            stencil.write_mask_counter_clockwise,
+            stencil.fail_operation_counter_clockwise,
+            stencil.depth_pass_operation_counter_clockwise
+        )
+    } else {
+        (
+            stencil.test_clockwise,
+            stencil.reference_value_clockwise,
+            stencil.write_mask_clockwise,
+            stencil.fail_operation_clockwise,
+            stencil.depth_pass_operation_clockwise
+        )
+    };
+
+    if area < 0f32 {
+        points.swap(1, 2);
+        vertices.swap(1, 2);
+        area = -area;
+    }
+
+    let barycentric = |point: Vector2| [
+        edge(points[1], points[2], point) / area,
+        edge(points[2], points[0], point) / area,
+        edge(points[0], points[1], point) / area
+    ];
+    let interpolate = |weights: [f32; 3], attribute: fn(&Vertex) -> [f32; 4]| {
+        let mut output = [0f32; 4];
+        for (vertex, weight) in vertices.iter().zip(weights) {
+            for (output, value) in output.iter_mut().zip(attribute(vertex)) {
+                *output += value * weight;
+            }
+        }
+
+        output
+    };
+    let uv = |vertex: &Vertex| [vertex.uv[0], vertex.uv[1], 0f32, 0f32];
+    let color = |vertex: &Vertex| vertex.color;
+
+    let layer = &layers[(vertices[0].index as usize).min(layers.len() - 1)];
+    let filter = {
+        let centroid = (points[0] + points[1] + points[2]) * (1f32 / 3f32);
+        let uv_center = interpolate(barycentric(centroid), uv);
+        let uv_right = interpolate(barycentric(centroid + Vector2::UNIT_X), uv);
+        let uv_down = interpolate(barycentric(centroid + Vector2::UNIT_Y), uv);
+        let texels_per_pixel = |uv_next: [f32; 4]| Vector2::new(
+            (uv_next[0] - uv_center[0]) * layer.width() as f32,
+            (uv_next[1] - uv_center[1]) * layer.height() as f32
+        ).distance(&Vector2::ZERO);
+
+        if texels_per_pixel(uv_right).max(texels_per_pixel(uv_down)) > 1f32 {
+            match sampler_behaviour.minify_filter {
+                MinifySamplerFilter::Nearest
+                | MinifySamplerFilter::NearestMipmapNearest
+                | MinifySamplerFilter::NearestMipmapLinear => Filter::Nearest,
+                _ => Filter::Linear
+            }
+        } else {
+            match sampler_behaviour.magnify_filter {
+                MagnifySamplerFilter::Nearest => Filter::Nearest,
+                MagnifySamplerFilter::Linear => Filter::Linear
+            }
+        }
+    };
+
+    // Like clipping in clip space, nothing is drawn outside of the viewport, nor outside of the scissor rectangle.
+    let scissor = draw_parameters.scissor.unwrap_or(Rect { left: 0, bottom: 0, width, height });
+    let scissor_top = height.saturating_sub(scissor.bottom + scissor.height);
+    let minimum_x = (points.iter().map(|point| point.x).fold(f32::INFINITY, f32::min).floor().max(viewport.left as f32) as u32)
+        .max(scissor.left);
+    let minimum_y = (points.iter().map(|point| point.y).fold(f32::INFINITY, f32::min).floor().max(viewport_top) as u32)
+        .max(scissor_top);
+    let maximum_x = (points.iter().map(|point| point.x).fold(f32::NEG_INFINITY, f32::max).ceil() as u32)
+        .min(viewport.left + viewport.width)
+        .min(scissor.left + scissor.width)
+        .min(width);
+    let maximum_y = (points.iter().map(|point| point.y).fold(f32::NEG_INFINITY, f32::max).ceil() as u32)
+        .min(height.saturating_sub(viewport.bottom))
+        .min(height.saturating_sub(scissor.bottom))
+        .min(height);
+    let edges = [(points[1], points[2]), (points[2], points[0]), (points[0], points[1])];
+
+    for y in minimum_y..maximum_y {
+        for x in minimum_x..maximum_x {
+            let point = Vector2::new(x as f32 + 0.5, y as f32 + 0.5);
+            let inside = edges.iter().all(
+                |(a, b)| {
+                    let value = edge(*a, *b, point);
+                    value > 0f32 || (value == 0f32 && is_top_left(*a, *b))
+                }
+            );
+
+            if !inside {
+                continue;
+            }
+
+            let weights = barycentric(point);
+            let uv = interpolate(weights, uv);
+            let color = interpolate(weights, color);
+            let texel = sample(layer, [uv[0], uv[1]], filter, sampler_behaviour.wrap_function);
+            let source = match shading {
+                Some((software_fragment, uniforms)) => software_fragment(
+                    &Fragment { uv: [uv[0], uv[1]], color, texel, position: (x, y) },
+                    uniforms
+                ),
+                None => [texel[0] * color[0], texel[1] * color[1], texel[2] * color[2], texel[3] * color[3]]
+            };
+
+            if source[3] < draw_call.alpha_cutoff {
+                continue;
+            }
+
+            let stencil_value = &mut canvas.stencil[(y * width + x) as usize];
+            let passes = stencil_passes(test, reference, *stencil_value);
+            let operation = if passes { pass_operation } else { fail_operation };
+            let operated = stencil_operation(operation, reference, *stencil_value);
+            *stencil_value = (*stencil_value & !write_mask as u8) | (operated & write_mask as u8);
+            if !passes {
+                continue;
+            }
+
+            let destination = canvas.image.get_pixel_mut(x, y);
+            let blended = blend(source, destination.0.map(to_f32), draw_parameters);
+            let (red, green, blue, alpha) = draw_parameters.color_mask;
+            for (channel, write) in [red, green, blue, alpha].into_iter().enumerate() {
+                if write {
+                    destination.0[channel] = to_u8(blended[channel]);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Filter {
+    Nearest,
+    Linear
+}
+
+fn edge(a: Vector2, b: Vector2, point: Vector2) -> f32 {
+    (b.x - a.x) * (point.y - a.y) - (b.y - a.y) * (point.x - a.x)
+}
+
+/// Pixels exactly on an edge are only covered by the triangle for which it is a top or left edge,
+/// so quads sharing a diagonal are not blended twice.
+fn is_top_left(a: Vector2, b: Vector2) -> bool {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    dy < 0f32 || (dy == 0f32 && dx > 0f32)
+}
+
+fn to_f32(value: u8) -> f32 {
+    value as f32 / 255f32
+}
+
+fn to_u8(value: f32) -> u8 {
+    (value.clamp(0f32, 1f32) * 255f32).round() as u8
+}
+
+fn wrap(coordinate: i64, size: u32, wrap_function: SamplerWrapFunction) -> Option<u32> {
+    let size = size as i64;
+    let wrapped = match wrap_function {
+        SamplerWrapFunction::Repeat => coordinate.rem_euclid(size),
+        SamplerWrapFunction::Mirror => {
+            let period = coordinate.rem_euclid(size * 2);
+            if period < size { period } else { size * 2 - 1 - period }
+        },
+        SamplerWrapFunction::Clamp => coordinate.clamp(0, size - 1),
+        SamplerWrapFunction::MirrorClamp => {
+            if coordinate < 0 { (-1 - coordinate).min(size - 1) } else { coordinate.min(size - 1) }
+        },
+        SamplerWrapFunction::BorderClamp => {
+            if coordinate < 0 || coordinate >= size {
+                return None;
+            }
+
+            coordinate
+        }
+    };
+
+    Some(wrapped as u32)
+}
+
+/// Fetches a texel with `(0, 0)` at the bottom-left of the layer, like the uploaded texture array.
+fn fetch(layer: &RgbaImage, x: i64, y: i64, wrap_function: (SamplerWrapFunction, SamplerWrapFunction, SamplerWrapFunction)) -> [f32; 4] {
+    let (Some(x), Some(y)) = (wrap(x, layer.width(), wrap_function.0), wrap(y, layer.height(), wrap_function.1)) else {
+        return [0f32; 4];
+    };
+
+    layer.get_pixel(x, layer.height() - 1 - y).0.map(to_f32)
+}
+
+fn sample(
+    layer: &RgbaImage,
+    uv: [f32; 2],
+    filter: Filter,
+    wrap_function: (SamplerWrapFunction, SamplerWrapFunction, SamplerWrapFunction)
+) -> [f32; 4] {
+    let x = uv[0] * layer.width() as f32;
+    let y = uv[1] * layer.height() as f32;
+    match filter {
+        Filter::Nearest => fetch(layer, x.floor() as i64, y.floor() as i64, wrap_function),
+        Filter::Linear => {
+            let (x, y) = (x - 0.5, y - 0.5);
+            let (left, bottom) = (x.floor(), y.floor());
+            let (horizontal, vertical) = (x - left, y - bottom);
+            let (left, bottom) = (left as i64, bottom as i64);
+
+            let corners = [
+                (fetch(layer, left, bottom, wrap_function), (1f32 - horizontal) * (1f32 - vertical)),
+                (fetch(layer, left + 1, bottom, wrap_function), horizontal * (1f32 - vertical)),
+                (fetch(layer, left, bottom + 1, wrap_function), (1f32 - horizontal) * vertical),
+                (fetch(layer, left + 1, bottom + 1, wrap_function), horizontal * vertical)
+            ];
+
+            let mut output = [0f32; 4];
+            for (texel, weight) in corners {
+                for (output, value) in output.iter_mut().zip(texel) {
+                    *output += value * weight;
+                }
+            }
+
+            output
+        }
+    }
+}
+
+fn stencil_passes(test: StencilTest, reference: i32, value: u8) -> bool {
+    let masked = |mask: u32| (reference as u32 & mask, value as u32 & mask);
+    match test {
+        StencilTest::AlwaysPass => true,
+        StencilTest::AlwaysFail => false,
+        StencilTest::IfLess { mask } => masked(mask).0 < masked(mask).1,
+        StencilTest::IfLessOrEqual { mask } => masked(mask).0 <= masked(mask).1,
+        StencilTest::IfMore { mask } => masked(mask).0 > masked(mask).1,
+        StencilTest::IfMoreOrEqual { mask } => masked(mask).0 >= masked(mask).1,
+        StencilTest::IfEqual { mask } => masked(mask).0 == masked(mask).1,
+        StencilTest::IfNotEqual { mask } => masked(mask).0 != masked(mask).1
+    }
+}
+
+fn stencil_operation(operation: StencilOperation, reference: i32, value: u8) -> u8 {
+    match operation {
+        StencilOperation::Keep => value,
+        StencilOperation::Zero => 0,
+        StencilOperation::Replace => reference.clamp(0, u8::MAX as i32) as u8,
+        StencilOperation::Increment => value.saturating_add(1),
+        StencilOperation::IncrementWrap => value.wrapping_add(1),
+        StencilOperation::Decrement => value.saturating_sub(1),
+        StencilOperation::DecrementWrap => value.wrapping_sub(1),
+        StencilOperation::Invert => !value
+    }
+}
+
+/// The factor for each channel; the alpha channel always uses the alpha variant of the factor.
+fn blend_factor(factor: LinearBlendingFactor, source: [f32; 4], destination: [f32; 4], constant: [f32; 4]) -> [f32; 4] {
+    let per_channel = |rgb: [f32; 3], alpha: f32| [rgb[0], rgb[1], rgb[2], alpha];
+    let splat = |value: f32| [value; 4];
+    let inverse = |values: [f32; 4]| values.map(|value| 1f32 - value);
+    match factor {
+        LinearBlendingFactor::Zero => splat(0f32),
+        LinearBlendingFactor::One => splat(1f32),
+        LinearBlendingFactor::SourceColor => source,
+        LinearBlendingFactor::OneMinusSourceColor => inverse(source),
+        LinearBlendingFactor::DestinationColor => destination,
+        LinearBlendingFactor::OneMinusDestinationColor => inverse(destination),
+        LinearBlendingFactor::SourceAlpha => splat(source[3]),
+        LinearBlendingFactor::SourceAlphaSaturate => {
+            let saturation = source[3].min(1f32 - destination[3]);
+            per_channel([saturation; 3], 1f32)
+        },
+        LinearBlendingFactor::OneMinusSourceAlpha => splat(1f32 - source[3]),
+        LinearBlendingFactor::DestinationAlpha => splat(destination[3]),
+        LinearBlendingFactor::OneMinusDestinationAlpha => splat(1f32 - destination[3]),
+        LinearBlendingFactor::ConstantColor => constant,
+        LinearBlendingFactor::OneMinusConstantColor => inverse(constant),
+        LinearBlendingFactor::ConstantAlpha => splat(constant[3]),
+        LinearBlendingFactor::OneMinusConstantAlpha => splat(1f32 - constant[3]),
+        // The default shader has no second output for dual source blending.
+        LinearBlendingFactor::SourceOneColor | LinearBlendingFactor::SourceOneAlpha => splat(0f32),
+        LinearBlendingFactor::OneMinusSourceOneColor | LinearBlendingFactor::OneMinusSourceOneAlpha => splat(1f32)
+    }
+}
+
+fn blend(source: [f32; 4], destination: [f32; 4], draw_parameters: &DrawParameters) -> [f32; 4] {
+    let blend = draw_parameters.blend;
+    let constant = [blend.constant_value.0, blend.constant_value.1, blend.constant_value.2, blend.constant_value.3];
+    let mut output = [0f32; 4];
+    for (channel, output) in output.iter_mut().enumerate() {
+        let function = if channel == 3 { blend.alpha } else { blend.color };
+        let factors = |source_factor, destination_factor| (
+            source[channel] * blend_factor(source_factor, source, destination, constant)[channel],
+            destination[channel] * blend_factor(destination_factor, source, destination, constant)[channel]
+        );
+
+        *output = match function {
+            BlendingFunction::AlwaysReplace => source[channel],
+            BlendingFunction::Min => source[channel].min(destination[channel]),
+            BlendingFunction::Max => source[channel].max(destination[channel]),
+            BlendingFunction::Addition { source, destination } => {
+                let (source, destination) = factors(source, destination);
+                source + destination
+            },
+            BlendingFunction::Subtraction { source, destination } => {
+                let (source, destination) = factors(source, destination);
+                source - destination
+            },
+            BlendingFunction::ReverseSubtraction { source, destination } => {
+                let (source, destination) = factors(source, destination);
+                destination - source
+            }
+        };
+    }
+
+    output
+}
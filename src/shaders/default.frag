@@ -1,6 +1,7 @@
 #version 140
 
 uniform sampler2DArray textures;
+uniform float alpha_cutoff;
 
 in vec2 out_uv;
 flat in uint out_index;
@@ -9,4 +10,7 @@ out vec4 fragment_color;
 
 void main() {
     fragment_color = texture(textures, vec3(out_uv, float(out_index))) * out_color;
+    if (fragment_color.a < alpha_cutoff) {
+        discard;
+    }
 }
\ No newline at end of file
@@ -3,7 +3,6 @@
 in uint index;
 in vec3 position;
 in vec2 uv;
-in mat4 matrix;
 in vec4 color;
 
 out vec2 out_uv;
@@ -15,5 +14,5 @@ void main() {
     out_index = index;
     out_color = color;
 
-    gl_Position = vec4(position, 1.0) * matrix;
+    gl_Position = vec4(position, 1.0);
 }
\ No newline at end of file
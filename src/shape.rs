@@ -0,0 +1,192 @@
+use std::f32::consts::TAU;
+
+use crate::math::{Rectangle, Vector2};
+
+/// Triangles drawn with `SpriteBatch::draw_shape`, batched with the sprites around them.
+/// Points are counterclockwise unless the shape is built from clockwise points.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Shape {
+    pub points: Vec<Vector2>,
+    /// Every three indices into `points` form a triangle.
+    pub indices: Vec<u32>
+}
+
+impl Shape {
+    pub fn new(points: Vec<Vector2>, indices: Vec<u32>) -> Self {
+        Self { points, indices }
+    }
+
+    /// A line of `thickness` centred on the segment from `start` to `end`, with flat ends.
+    pub fn line(start: Vector2, end: Vector2, thickness: f32) -> Self {
+        Self::stroke(&[start, end], thickness, false)
+    }
+
+    pub fn rectangle(rectangle: Rectangle) -> Self {
+        Self::polygon(&corners(rectangle))
+    }
+
+    /// The edges of `rectangle`, drawn inwards so the outline stays within it.
+    pub fn rectangle_outline(rectangle: Rectangle, thickness: f32) -> Self {
+        let thickness = thickness.min(rectangle.width / 2f32).min(rectangle.height / 2f32);
+        let inner = Rectangle::new(
+            rectangle.position.x + thickness,
+            rectangle.position.y + thickness,
+            rectangle.width - thickness * 2f32,
+            rectangle.height - thickness * 2f32
+        );
+
+        let mut points = corners(rectangle).to_vec();
+        points.extend(corners(inner));
+        Self::new(points, ring_indices(4, true))
+    }
+
+    /// An ellipse approximated by a polygon of `segments` sides, at least 3.
+    pub fn ellipse(center: Vector2, radii: Vector2, segments: u32) -> Self {
+        Self::polygon(&arc_points(center, radii, 0f32, TAU, segments.max(3), false))
+    }
+
+    pub fn circle(center: Vector2, radius: f32, segments: u32) -> Self {
+        Self::ellipse(center, Vector2::ONE * radius, segments)
+    }
+
+    /// The outline of `Shape::ellipse`, centred on its edges.
+    pub fn ellipse_outline(center: Vector2, radii: Vector2, segments: u32, thickness: f32) -> Self {
+        Self::stroke(&arc_points(center, radii, 0f32, TAU, segments.max(3), false), thickness, true)
+    }
+
+    pub fn circle_outline(center: Vector2, radius: f32, segments: u32, thickness: f32) -> Self {
+        Self::ellipse_outline(center, Vector2::ONE * radius, segments, thickness)
+    }
+
+    /// The part of a circle outline from `start_angle` to `end_angle`, in radians counterclockwise from the positive x axis.
+    pub fn arc(center: Vector2, radius: f32, start_angle: f32, end_angle: f32, segments: u32, thickness: f32) -> Self {
+        let points = arc_points(center, Vector2::ONE * radius, start_angle, end_angle, segments.max(1), true);
+        Self::stroke(&points, thickness, false)
+    }
+
+    /// Fills a simple polygon, convex or concave, whose points go around it in either direction.
+    /// Points that cross edges of the polygon are not supported, and may leave parts of it empty.
+    pub fn polygon(points: &[Vector2]) -> Self {
+        Self::new(points.to_vec(), triangulate(points))
+    }
+
+    /// A line of `thickness` centred on the segments between `points`, with mitered joints.
+    /// If `closed` is set, the last point is joined back to the first.
+    pub fn stroke(points: &[Vector2], thickness: f32, closed: bool) -> Self {
+        if points.len() < 2 {
+            return Self::default();
+        }
+
+        let half_thickness = thickness / 2f32;
+        let normal = |from: Vector2, to: Vector2| (to - from).normalized().perpendicular();
+        let mut right = Vec::with_capacity(points.len());
+        let mut left = Vec::with_capacity(points.len());
+        for (index, point) in points.iter().enumerate() {
+            let previous = match index {
+                0 if closed => points.last(),
+                0 => None,
+                _ => points.get(index - 1)
+            }.map(|previous| normal(*previous, *point));
+            let next = match points.get(index + 1) {
+                Some(next) => Some(normal(*point, *next)),
+                None if closed => Some(normal(*point, points[0])),
+                None => None
+            };
+
+            let offset = match (previous, next) {
+                (Some(previous), Some(next)) => {
+                    let miter = (previous + next).normalized();
+                    // Sharp corners would make the miter grow without limit, so it is capped at 4 times the thickness.
+                    miter * (half_thickness / miter.dot(&next).max(0.125))
+                },
+                (Some(normal), None) | (None, Some(normal)) => normal * half_thickness,
+                (None, None) => unreachable!()
+            };
+            right.push(*point - offset);
+            left.push(*point + offset);
+        }
+
+        Self::new([right, left].concat(), ring_indices(points.len() as u32, closed))
+    }
+}
+
+fn corners(rectangle: Rectangle) -> [Vector2; 4] {
+    [
+        rectangle.position,
+        Vector2::new(rectangle.right(), rectangle.position.y),
+        Vector2::new(rectangle.right(), rectangle.top()),
+        Vector2::new(rectangle.position.x, rectangle.top())
+    ]
+}
+
+/// `segments` points around an ellipse, or `segments + 1` if `inclusive` so the last one lands on `end_angle`.
+fn arc_points(center: Vector2, radii: Vector2, start_angle: f32, end_angle: f32, segments: u32, inclusive: bool) -> Vec<Vector2> {
+    let count = if inclusive { segments + 1 } else { segments };
+    (0..count).map(
+        |segment| {
+            let angle = start_angle + (end_angle - start_angle) * segment as f32 / segments as f32;
+            center + Vector2::new(angle.cos() * radii.x, angle.sin() * radii.y)
+        }
+    ).collect()
+}
+
+/// The quads between two rows of `length` points, the first row followed by the second.
+fn ring_indices(length: u32, closed: bool) -> Vec<u32> {
+    let quads = if closed { length } else { length - 1 };
+    (0..quads).flat_map(
+        |first| {
+            let second = (first + 1) % length;
+            [first, second, length + second, length + second, length + first, first]
+        }
+    ).collect()
+}
+
+/// Splits a simple polygon into triangles by repeatedly cutting off ears, corners whose triangle contains no other point.
+fn triangulate(points: &[Vector2]) -> Vec<u32> {
+    if points.len() < 3 {
+        return Vec::new();
+    }
+
+    let signed_area: f32 = points.iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(a, b)| a.cross(b))
+        .sum();
+    let mut remaining: Vec<u32> = (0..points.len() as u32).collect();
+    if signed_area < 0f32 {
+        remaining.reverse();
+    }
+
+    let point = |index: u32| points[index as usize];
+    let mut indices = Vec::with_capacity((points.len() - 2) * 3);
+    while remaining.len() > 3 {
+        let length = remaining.len();
+        let ear = (0..length).find(
+            |&corner| {
+                let [a, b, c] = [length + corner - 1, corner, corner + 1].map(|index| remaining[index % length]);
+                let (a_point, b_point, c_point) = (point(a), point(b), point(c));
+                (b_point - a_point).cross(&(c_point - b_point)) > 0f32 && !remaining.iter()
+                    .filter(|&&other| other != a && other != b && other != c)
+                    .any(|&other| contains(a_point, b_point, c_point, point(other)))
+            }
+        );
+
+        // Only self-intersecting or degenerate polygons run out of ears.
+        let Some(ear) = ear else {
+            break;
+        };
+
+        indices.extend([length + ear - 1, ear, ear + 1].map(|index| remaining[index % length]));
+        remaining.remove(ear);
+    }
+
+    if remaining.len() == 3 {
+        indices.extend_from_slice(&remaining);
+    }
+
+    indices
+}
+
+/// Whether `point` is inside or on the edges of the counterclockwise triangle `a`, `b`, `c`.
+fn contains(a: Vector2, b: Vector2, c: Vector2, point: Vector2) -> bool {
+    (b - a).cross(&(point - a)) >= 0f32 && (c - b).cross(&(point - b)) >= 0f32 && (a - c).cross(&(point - c)) >= 0f32
+}
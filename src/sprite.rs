@@ -1,15 +1,33 @@
+use std::{fmt::{self, Formatter}, error::Error, fs, path::{Path, PathBuf}, time::SystemTime};
 use defaults::Defaults;
 use glium::{texture::{Texture2dArray, RawImage2d, TextureCreationError}, glutin::surface::WindowSurface, Display};
-use image::{RgbaImage, ImageBuffer, Rgba};
+use image::{RgbaImage, ImageBuffer, ImageError, Rgba, imageops};
 
-#[derive(Clone, Copy, Debug, Defaults)]
+use crate::{
+    atlas::ShelfPacker,
+    effect::{Shader, ShaderSource},
+    math::Rectangle,
+    render_target::RenderTarget,
+    sprite_sheet::{Grid, SpriteSheet, TextureRegion}
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Defaults)]
 pub struct Sprite {
+    /// Tells apart the sprites a loader placed in the same space, once an earlier one was unloaded.
+    id: u32,
     index: u32,
     #[def = "(1u32, 1u32)"]
-    dimensions: (u32, u32)
+    dimensions: (u32, u32),
+    position: (u32, u32),
+    render_target: Option<u32>
 }
 
 impl Sprite {
+    pub(crate) fn from_render_target(render_target: RenderTarget) -> Self {
+        Self { id: 0, index: 0, dimensions: render_target.dimensions(), position: (0, 0), render_target: Some(render_target.id()) }
+    }
+
+    /// The texture array layer the sprite is stored in.
     pub fn index(&self) -> u32 {
         self.index
     }
@@ -17,58 +35,305 @@ impl Sprite {
     pub fn dimensions(&self) -> (u32, u32) {
         self.dimensions
     }
+
+    /// The bottom-left corner of the sprite in its texture array layer.
+    pub fn position(&self) -> (u32, u32) {
+        self.position
+    }
+
+    /// The render target the sprite shows, if it is not stored in the texture array.
+    pub fn render_target(&self) -> Option<RenderTarget> {
+        self.render_target.map(|id| RenderTarget::new(id, self.dimensions))
+    }
+
+    /// The part of the sprite covered by `source`, measured from its bottom-left corner.
+    pub fn region(&self, source: Rectangle) -> TextureRegion {
+        TextureRegion { sprite: *self, source }
+    }
+
+    /// Slices the sprite into `grid`, as described by `SpriteSheet::new`.
+    pub fn slice(&self, grid: &Grid) -> SpriteSheet {
+        SpriteSheet::new(*self, grid)
+    }
+}
+
+#[derive(Debug)]
+pub enum SpriteError {
+    /// The sprite was never loaded by this loader or has been unloaded.
+    UnknownSprite,
+    /// A replacement image must have the dimensions of the sprite it replaces.
+    DimensionsMismatch { expected: (u32, u32), actual: (u32, u32) },
+    /// The grid of a sprite sheet has empty cells or asks for more columns or rows than fit in the sprite.
+    InvalidGrid { grid: Grid, dimensions: (u32, u32) },
+    Image(ImageError)
+}
+
+impl fmt::Display for SpriteError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownSprite => write!(f, "the sprite is not loaded"),
+            Self::DimensionsMismatch { expected, actual } => write!(f, "expected a {expected:?} image, got {actual:?}"),
+            Self::InvalidGrid { grid, dimensions } => write!(f, "the grid of {:?} cells does not fit in a {dimensions:?} sprite", grid.cell_dimensions),
+            Self::Image(error) => write!(f, "failed to read the image: {error}")
+        }
+    }
+}
+
+impl Error for SpriteError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Image(error) => Some(error),
+            _ => None
+        }
+    }
 }
 
+struct WatchedFile {
+    sprite: Sprite,
+    path: PathBuf,
+    modified: Option<SystemTime>
+}
+
+/// Keeps the images of every loaded sprite so the texture array can be rebuilt whenever sprites are
+/// loaded or unloaded, and updated in place when they are replaced. Sprites never move, so their handles stay valid across rebuilds.
+#[derive(Default)]
 pub struct SpriteLoader {
-    images: Vec<RgbaImage>,
+    /// Every loaded sprite, its image and the `version` its pixels last changed at.
+    images: Vec<(Sprite, RgbaImage, u64)>,
+    packer: Option<ShelfPacker>,
+    free_layers: Vec<u32>,
+    watched_files: Vec<WatchedFile>,
+    next_sprite: u32,
+    render_targets: Vec<RenderTarget>,
+    next_render_target: u32,
+    shaders: Vec<(Shader, ShaderSource)>,
+    next_shader: u32,
+    modified: bool,
+    /// Counts the loads, replacements and unloads of sprites.
+    version: u64,
+    /// The `version` at which a sprite was last loaded or unloaded.
+    layout_version: u64
 }
 
 impl SpriteLoader {
+    /// Creates a loader that gives every sprite its own texture array layer, padded to the size of the largest sprite.
     pub fn new() -> Self {
-        Self { images: Vec::new() }
+        Self::default()
+    }
+
+    /// Creates a loader that packs many sprites into each texture array layer of `width` by `height` pixels.
+    pub fn new_atlas(width: u32, height: u32) -> Self {
+        Self { packer: Some(ShelfPacker::new((width, height), 1)), ..Self::default() }
     }
 
+    /// # Panics
+    /// Panics if the loader packs an atlas and the image is larger than its layers.
     pub fn load_sprite(&mut self, image: ImageBuffer<Rgba<u8>, Vec<u8>>) -> Sprite {
+        let id = self.next_sprite;
+        self.next_sprite += 1;
         let dimensions = image.dimensions();
-        self.images.push(image);
-        Sprite { index: (self.images.len() - 1) as u32, dimensions }
+        let sprite = match &mut self.packer {
+            Some(packer) => {
+                let Some((index, position)) = packer.pack(dimensions) else {
+                    panic!("a {dimensions:?} sprite does not fit in {:?} atlas layers", packer.layer_dimensions());
+                };
+
+                Sprite { id, index, dimensions, position, render_target: None }
+            },
+            None => {
+                self.free_layers.sort_unstable_by(|a, b| b.cmp(a));
+                let index = self.free_layers.pop().unwrap_or(self.images.len() as u32);
+                Sprite { id, index, dimensions, position: (0, 0), render_target: None }
+            }
+        };
+
+        self.version += 1;
+        self.layout_version = self.version;
+        self.images.push((sprite, image, self.version));
+        self.modified = true;
+        sprite
     }
 
-    pub fn create_texture_array(mut self, display: &Display<WindowSurface>) -> Result<Texture2dArray, TextureCreationError> {
-        let mut max_width = 0;
-        let mut max_height = 0;
-        for image in self.images.iter_mut() {
-            if image.width() > max_width {
-                max_width = image.width();
+    /// Loads a sprite from an image file and watches it, so `reload_modified` can pick up changes made on disk.
+    pub fn load_sprite_file(&mut self, path: impl AsRef<Path>) -> Result<Sprite, SpriteError> {
+        let path = path.as_ref().to_path_buf();
+        let modified = modified_time(&path);
+        let image = image::open(&path).map_err(SpriteError::Image)?.into_rgba8();
+        let sprite = self.load_sprite(image);
+        self.watched_files.push(WatchedFile { sprite, path, modified });
+        Ok(sprite)
+    }
+
+    /// Loads a sprite from an image file, as described by `load_sprite_file`, and slices it into `grid`.
+    /// The sprite is unloaded again if the grid does not fit in it.
+    pub fn load_sprite_sheet_file(&mut self, path: impl AsRef<Path>, grid: &Grid) -> Result<SpriteSheet, SpriteError> {
+        let sprite = self.load_sprite_file(path)?;
+        SpriteSheet::try_new(sprite, grid).inspect_err(
+            |_| {
+                self.unload_sprite(sprite).unwrap();
             }
+        )
+    }
 
-            if image.height() > max_height {
-                max_height = image.height();
+    /// Re-reads every watched file whose modification time changed and replaces the pixels of its sprite.
+    /// Returns the files that could not be reloaded, such as half-written ones; they are retried on their next change.
+    pub fn reload_modified(&mut self) -> Vec<(PathBuf, SpriteError)> {
+        let mut errors = Vec::new();
+        for index in 0..self.watched_files.len() {
+            let watched_file = &mut self.watched_files[index];
+            let modified = modified_time(&watched_file.path);
+            if modified == watched_file.modified {
+                continue;
             }
-        }
 
-        self.images = self.images.iter().map(
-            |image| {
-                let mut new_image = RgbaImage::new(max_width, max_height);
-                for (i, j, pixel) in new_image.enumerate_pixels_mut() {
-                    if i < image.width() && j < image.height() {
-                        *pixel = *image.get_pixel(i, j);
-                    } else {
-                        *pixel = Rgba([0, 0, 0, 0]);
-                    }
-                }
+            watched_file.modified = modified;
+            let (sprite, path) = (watched_file.sprite, watched_file.path.clone());
+            let result = image::open(&path)
+                .map_err(SpriteError::Image)
+                .and_then(|image| self.replace_sprite(sprite, image.into_rgba8()));
 
-                new_image
+            if let Err(error) = result {
+                errors.push((path, error));
             }
-        ).collect();
+        }
+
+        errors
+    }
+
+    /// Replaces the pixels of a loaded sprite, keeping its handle valid.
+    pub fn replace_sprite(&mut self, sprite: Sprite, image: RgbaImage) -> Result<(), SpriteError> {
+        let (_, current_image, version) = self.images.iter_mut().find(|(loaded, _, _)| *loaded == sprite).ok_or(SpriteError::UnknownSprite)?;
+        if image.dimensions() != sprite.dimensions {
+            return Err(SpriteError::DimensionsMismatch { expected: sprite.dimensions, actual: image.dimensions() });
+        }
+
+        self.version += 1;
+        *current_image = image;
+        *version = self.version;
+        self.modified = true;
+        Ok(())
+    }
+
+    /// Frees the space of a sprite so later sprites can reuse it. Drawing the sprite afterwards draws whatever replaces it,
+    /// but replacing or unloading it again fails with `SpriteError::UnknownSprite`.
+    pub fn unload_sprite(&mut self, sprite: Sprite) -> Result<(), SpriteError> {
+        let position = self.images.iter().position(|(loaded, _, _)| *loaded == sprite).ok_or(SpriteError::UnknownSprite)?;
+        self.images.swap_remove(position);
+        self.version += 1;
+        self.layout_version = self.version;
+        self.watched_files.retain(|watched_file| watched_file.sprite != sprite);
+        match &mut self.packer {
+            Some(packer) => packer.free(sprite.index, sprite.position),
+            None => self.free_layers.push(sprite.index)
+        }
+
+        self.modified = true;
+        Ok(())
+    }
+
+    /// Reserves an offscreen texture of `width` by `height` pixels. Like sprites, it is created by the renderer
+    /// once the loader is marked as modified, and starts out transparent.
+    pub fn create_render_target(&mut self, width: u32, height: u32) -> RenderTarget {
+        let render_target = RenderTarget::new(self.next_render_target, (width, height));
+        self.next_render_target += 1;
+        self.render_targets.push(render_target);
+        self.modified = true;
+        render_target
+    }
+
+    /// Releases the texture of a render target. Drawing into it or drawing its sprite afterwards fails.
+    pub fn free_render_target(&mut self, render_target: RenderTarget) {
+        self.render_targets.retain(|created| *created != render_target);
+        self.modified = true;
+    }
+
+    pub fn render_targets(&self) -> &[RenderTarget] {
+        &self.render_targets
+    }
+
+    /// Keeps the source of a shader for the renderer to compile once the loader is marked as modified.
+    pub fn load_shader(&mut self, source: ShaderSource) -> Shader {
+        let shader = Shader::new(self.next_shader);
+        self.next_shader += 1;
+        self.shaders.push((shader, source));
+        self.modified = true;
+        shader
+    }
+
+    /// Frees a shader. Drawing with an effect using it afterwards fails.
+    pub fn unload_shader(&mut self, shader: Shader) {
+        self.shaders.retain(|(loaded, _)| *loaded != shader);
+        self.modified = true;
+    }
+
+    pub fn shaders(&self) -> &[(Shader, ShaderSource)] {
+        &self.shaders
+    }
 
+    /// Returns whether sprites, render targets or shaders were added, replaced or removed since the last call, meaning the renderer needs updating.
+    pub fn take_modified(&mut self) -> bool {
+        std::mem::take(&mut self.modified)
+    }
+
+    /// Counts the changes to the pixels of the loaded sprites, so a renderer can tell which ones it has not uploaded yet.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// The sprites replaced after `version`, with their new images. Returns `None` if sprites were loaded or
+    /// unloaded since, in which case the layers need to be created again.
+    pub fn replaced_since(&self, version: u64) -> Option<Vec<(Sprite, &RgbaImage)>> {
+        if self.layout_version > version {
+            return None;
+        }
+
+        Some(
+            self.images.iter()
+                .filter(|(_, _, replaced)| *replaced > version)
+                .map(|(sprite, image, _)| (*sprite, image))
+                .collect()
+        )
+    }
+
+    /// Copies every image into its texture array layer. Layers are stored top row first, so each sprite
+    /// is placed with its bottom-left corner at its `position`, where its texture coordinates start.
+    pub fn create_layers(&self) -> Vec<RgbaImage> {
+        let (layer_width, layer_height) = match &self.packer {
+            Some(packer) => packer.layer_dimensions(),
+            None => (
+                self.images.iter().map(|(_, image, _)| image.width()).max().unwrap_or(0),
+                self.images.iter().map(|(_, image, _)| image.height()).max().unwrap_or(0)
+            )
+        };
+        let layer_count = self.images.iter().map(|(sprite, _, _)| sprite.index + 1).max().unwrap_or(0);
+
+        let mut layers = vec![RgbaImage::new(layer_width, layer_height); layer_count as usize];
+        for (sprite, image, _) in self.images.iter() {
+            copy_to_layer(&mut layers[sprite.index as usize], *sprite, image);
+        }
+
+        layers
+    }
+
+    pub fn create_texture_array(&self, display: &Display<WindowSurface>) -> Result<Texture2dArray, TextureCreationError> {
         Texture2dArray::new(
             display, 
-            self.images.iter().map(
+            self.create_layers().iter().map(
                 |image| {
                     RawImage2d::from_raw_rgba_reversed(image.as_raw(), image.dimensions())
                 }
             ).collect()
         )
     }
-}
\ No newline at end of file
+}
+
+/// Copies the image of `sprite` into its layer, which is stored top row first, with its bottom-left corner at the position of the sprite.
+pub(crate) fn copy_to_layer(layer: &mut RgbaImage, sprite: Sprite, image: &RgbaImage) {
+    let (x, y) = sprite.position;
+    let layer_height = layer.height();
+    imageops::replace(layer, image, x as i64, (layer_height - y - image.height()) as i64);
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
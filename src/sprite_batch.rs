@@ -1,23 +1,37 @@
-use std::rc::Rc;
+use std::{fmt::{self, Formatter}, error::Error};
 use defaults::Defaults;
 use glium::{
-    DrawParameters, 
-    Program, 
-    implement_vertex, 
-    Display, 
-    glutin::surface::WindowSurface,
-    VertexBuffer, 
-    IndexBuffer, 
-    index::PrimitiveType, 
-    texture::Texture2dArray, 
-    Surface, 
-    uniform, uniforms::{Sampler, SamplerBehavior}
+    Blend,
+    BlendingFunction,
+    DrawParameters,
+    DrawError,
+    LinearBlendingFactor,
+    Rect,
+    StencilOperation,
+    StencilTest,
+    draw_parameters::Stencil,
+    framebuffer::ValidationError,
+    texture::TextureCreationError,
+    uniforms::SamplerBehavior,
+    vertex,
+    index
 };
-use winit::window::Window;
 
-use crate::{math::{Matrix4x4, Vector2, Rectangle}, sprite::Sprite, color::Color};
+use crate::{
+    camera::Camera2D,
+    effect::Effect,
+    font::{Font, FontError, TextLayout, TextOptions},
+    math::{Matrix4x4, Vector2, Rectangle},
+    mesh::Mesh,
+    nine_slice::NineSlice,
+    render_target::{RenderTarget, RenderTargetWrite},
+    renderer::{DrawCall, Renderer},
+    shape::Shape,
+    sprite::Sprite,
+    color::Color
+};
 
-#[derive(Defaults)]
+#[derive(Clone, Defaults)]
 pub struct DrawData {
     pub sprite: Sprite,
     pub position: Vector2,
@@ -26,158 +40,540 @@ pub struct DrawData {
     pub origin: Vector2,
     #[def = "Color::new(1f32, 1f32, 1f32, 1f32)"]
     pub color: Color,
+    /// Colours of the bottom-left, bottom-right, top-right and top-left corners, in the space of the sprite before it is transformed.
+    /// They are interpolated across the sprite and multiplied by `color`.
+    pub corner_colors: Option<[Color; 4]>,
     pub depth: f32,
     #[def = "Vector2::ONE"]
-    pub scale: Vector2
+    pub scale: Vector2,
+    /// Mirrors the texture horizontally without moving the sprite, unlike a negative scale which also mirrors the origin.
+    pub flip_x: bool,
+    /// Mirrors the texture vertically without moving the sprite.
+    pub flip_y: bool,
+    /// An extra transform, such as `Matrix4x4::new_skew`, applied around the origin after the scale and before the rotation.
+    pub transform: Option<Matrix4x4>,
+    /// Overrides `SpriteBatch::draw_parameters.blend` for this sprite.
+    pub blend_mode: Option<BlendMode>
+}
+
+/// The common ways of blending a sprite with what is already drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// Covers the destination by the alpha of the sprite.
+    Alpha,
+    /// Like `Alpha`, for sprites whose colour is already multiplied by their alpha.
+    PremultipliedAlpha,
+    /// Adds the colour of the sprite, weighted by its alpha, to the destination.
+    Additive,
+    /// Multiplies the destination by the colour of the sprite, darkening it.
+    /// Like `PremultipliedAlpha`, the colour must already be multiplied by its alpha, so transparent pixels leave the destination unchanged.
+    Multiply,
+    /// Multiplies the inverse of the destination by the inverse of the sprite colour, lightening it.
+    /// Like `Multiply`, the colour must already be multiplied by its alpha.
+    Screen,
+    /// Replaces the destination, ignoring alpha.
+    Opaque
+}
+
+impl BlendMode {
+    pub fn blend(&self) -> Blend {
+        let addition = |source, destination| BlendingFunction::Addition { source, destination };
+        let (color, alpha) = match self {
+            Self::Alpha => return Blend::alpha_blending(),
+            Self::PremultipliedAlpha => (
+                addition(LinearBlendingFactor::One, LinearBlendingFactor::OneMinusSourceAlpha),
+                addition(LinearBlendingFactor::One, LinearBlendingFactor::OneMinusSourceAlpha)
+            ),
+            Self::Additive => (
+                addition(LinearBlendingFactor::SourceAlpha, LinearBlendingFactor::One),
+                addition(LinearBlendingFactor::Zero, LinearBlendingFactor::One)
+            ),
+            Self::Multiply => (
+                addition(LinearBlendingFactor::DestinationColor, LinearBlendingFactor::OneMinusSourceAlpha),
+                addition(LinearBlendingFactor::One, LinearBlendingFactor::OneMinusSourceAlpha)
+            ),
+            Self::Screen => (
+                addition(LinearBlendingFactor::One, LinearBlendingFactor::OneMinusSourceColor),
+                addition(LinearBlendingFactor::One, LinearBlendingFactor::OneMinusSourceAlpha)
+            ),
+            Self::Opaque => (BlendingFunction::AlwaysReplace, BlendingFunction::AlwaysReplace)
+        };
+
+        Blend { color, alpha, constant_value: (0f32, 0f32, 0f32, 0f32) }
+    }
+}
+
+/// How sprites interact with the stencil buffer, to limit drawing to arbitrarily shaped areas.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MaskMode {
+    /// Sprites are not drawn, but add the pixels where their alpha is at least `alpha_cutoff` to the mask.
+    Write { alpha_cutoff: f32 },
+    /// Sprites are only drawn inside the mask.
+    Inside,
+    /// Sprites are only drawn outside of the mask.
+    Outside
+}
+
+impl MaskMode {
+    pub fn stencil(&self) -> Stencil {
+        let test = match self {
+            Self::Write { .. } => StencilTest::AlwaysPass,
+            Self::Inside => StencilTest::IfEqual { mask: u32::MAX },
+            Self::Outside => StencilTest::IfNotEqual { mask: u32::MAX }
+        };
+        let operation = match self {
+            Self::Write { .. } => StencilOperation::Replace,
+            Self::Inside | Self::Outside => StencilOperation::Keep
+        };
+
+        Stencil {
+            test_clockwise: test,
+            reference_value_clockwise: 1,
+            depth_pass_operation_clockwise: operation,
+            test_counter_clockwise: test,
+            reference_value_counter_clockwise: 1,
+            depth_pass_operation_counter_clockwise: operation,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SpriteBatchError {
+    VertexBufferCreation(vertex::BufferCreationError),
+    IndexBufferCreation(index::BufferCreationError),
+    Draw(DrawError),
+    FramebufferCreation(ValidationError),
+    TextureCreation(TextureCreationError),
+    /// The render target was never created by the loader of the renderer's textures or has been freed.
+    UnknownRenderTarget,
+    /// The shader of an effect was never loaded by the loader of the renderer's shaders or has been unloaded.
+    UnknownShader,
+    /// Text drawn during the segment is missing glyphs. The rest of the segment is still drawn.
+    Font(FontError)
+}
+
+impl fmt::Display for SpriteBatchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::VertexBufferCreation(error) => write!(f, "failed to create the vertex buffer: {error}"),
+            Self::IndexBufferCreation(error) => write!(f, "failed to create the index buffer: {error}"),
+            Self::Draw(error) => write!(f, "failed to draw the batch: {error}"),
+            Self::FramebufferCreation(error) => write!(f, "failed to create the render target framebuffer: {error}"),
+            Self::TextureCreation(error) => write!(f, "failed to create a texture: {error}"),
+            Self::UnknownRenderTarget => write!(f, "the render target does not exist"),
+            Self::UnknownShader => write!(f, "the shader is not loaded"),
+            Self::Font(error) => write!(f, "failed to draw text: {error}")
+        }
+    }
+}
+
+impl Error for SpriteBatchError { }
+
+/// Controls the order in which the sprites of a batch are submitted.
+/// Sorting is stable, so sprites that compare equal keep their submission order.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SpriteSortMode {
+    /// Sprites are drawn in the order of the `draw` calls.
+    #[default]
+    Deferred,
+    /// Sprites with the greatest depth are drawn first.
+    BackToFront,
+    /// Sprites with the smallest depth are drawn first.
+    FrontToBack,
+    /// Sprites are grouped by their effect, render target and texture array layer.
+    Texture,
+    /// Every sprite is submitted with its own draw call, in the order of the `draw` calls.
+    /// `SpriteBatch::draw_immediate` submits a sprite as soon as it is drawn, for debugging.
+    Immediate
 }
 
-#[derive(Clone, Copy, Default)]
-struct Vertex {
-    index: u32,
-    position: [f32; 3],
-    uv: [f32; 2],
-    color: [f32; 4],
-    matrix: [[f32; 4]; 4]
+impl SpriteSortMode {
+    /// Sprites are never moved past a change of the mask mode, so masks are written before they are used.
+    fn sort(&self, draws: &mut [CachedDraw]) {
+        match self {
+            Self::Deferred | Self::Immediate => (),
+            Self::BackToFront => draws.sort_by(|a, b| a.stage.cmp(&b.stage).then(b.draw_data.depth.total_cmp(&a.draw_data.depth))),
+            Self::FrontToBack => draws.sort_by(|a, b| a.stage.cmp(&b.stage).then(a.draw_data.depth.total_cmp(&b.draw_data.depth))),
+            Self::Texture => draws.sort_by_key(
+                |draw| (
+                    draw.stage,
+                    draw.effect,
+                    draw.draw_data.sprite.render_target().map(|render_target| render_target.id()),
+                    draw.draw_data.sprite.index()
+                )
+            )
+        }
+    }
+}
+
+/// A sprite or shape waiting for `end`, with the batch state it was drawn with.
+struct CachedDraw {
+    /// The index of the effect in `SpriteBatch::effects`.
+    effect: usize,
+    clip: Option<Rectangle>,
+    mask: Option<MaskMode>,
+    /// How many times the mask mode changed during the segment before this draw.
+    stage: usize,
+    /// Drawn instead of the quad of the sprite.
+    shape: Option<Shape>,
+    draw_data: DrawData
 }
 
-implement_vertex!(Vertex, index, position, uv, color, matrix);
 pub struct SpriteBatch<'a> {
     pub draw_parameters: DrawParameters<'a>,
     pub sampler_behaviour: SamplerBehavior,
-    pub program: Program,
-    window: Rc<Window>,
-    display: Rc<Display<WindowSurface>>,
-    draw_data_cache: Vec<DrawData>,
-    texture_array: Texture2dArray
+    pub camera: Camera2D,
+    sort_mode: Option<SpriteSortMode>,
+    /// The render target of the current segment and the camera viewport to restore once it ends.
+    render_target: Option<(RenderTarget, (u32, u32))>,
+    /// The effects set during the current segment; the last one applies to the next `draw`.
+    effects: Vec<Option<Effect>>,
+    /// The intersections of the pushed clip rectangles, the last one applying to the next `draw`.
+    clips: Vec<Rectangle>,
+    mask: Option<MaskMode>,
+    stage: usize,
+    /// Identifies the current segment to the fonts drawn in it, so they keep its glyphs until it ends.
+    segment: u64,
+    /// Written by `end` before drawing, so the sprites of the segment can show them.
+    render_target_writes: Vec<RenderTargetWrite>,
+    /// The first error of the fonts drawn during the segment, returned by `end`.
+    font_error: Option<FontError>,
+    draw_data_cache: Vec<CachedDraw>
 }
 
 impl <'a> SpriteBatch<'a> {
-    pub fn new(
-        window: Rc<Window>,
-        display: Rc<Display<WindowSurface>>,
-        program: Program,
-        texture_array: Texture2dArray
-    ) -> Self {
+    pub fn new(viewport: (u32, u32)) -> Self {
         Self { 
             draw_parameters: DrawParameters::default(), 
             sampler_behaviour: SamplerBehavior::default(), 
-            window,
-            display,
-            program,
-            draw_data_cache: Vec::new(),
-            texture_array
+            camera: Camera2D::new(viewport),
+            sort_mode: None,
+            render_target: None,
+            effects: vec![None],
+            clips: Vec::new(),
+            mask: None,
+            stage: 0,
+            segment: 0,
+            render_target_writes: Vec::new(),
+            font_error: None,
+            draw_data_cache: Vec::new()
         }
     }
 
+    /// Fills the area visible through the camera with `color`.
     pub fn clear_color(&mut self, color: Color) {
-        let (window_width, window_height) = (self.window.inner_size().width as f32, self.window.inner_size().height as f32);
         self.draw(
             DrawData { 
-                position: Vector2::new(-window_width / 2f32, -window_height / 2f32),
+                position: self.camera.position,
+                rotation: self.camera.rotation,
+                origin: Vector2::ONE * 0.5,
                 color, 
-                scale: Vector2::new(window_width, window_height),
+                scale: self.camera.visible_size(),
                 ..Default::default()
             }
         )
     }
 
+    /// Fills the area visible through the camera with a gradient between the colours of its bottom-left,
+    /// bottom-right, top-right and top-left corners.
+    pub fn clear_gradient(&mut self, corner_colors: [Color; 4]) {
+        self.draw(
+            DrawData { 
+                position: self.camera.position,
+                rotation: self.camera.rotation,
+                origin: Vector2::ONE * 0.5,
+                corner_colors: Some(corner_colors),
+                scale: self.camera.visible_size(),
+                ..Default::default()
+            }
+        )
+    }
+
+    /// Starts a batch segment. Every `draw` until the matching `end` is sorted with `sort_mode`
+    /// and submitted with the `draw_parameters` and `sampler_behaviour` set at the time of `end`.
+    /// Unless `draw_parameters.viewport` is set, the segment is drawn onto the letterbox of the camera.
+    pub fn begin(&mut self, sort_mode: SpriteSortMode) {
+        assert!(self.sort_mode.is_none(), "SpriteBatch::begin called twice without SpriteBatch::end");
+        self.sort_mode = Some(sort_mode);
+        self.segment += 1;
+    }
+
+    /// Starts a batch segment that is drawn into `render_target` instead of the surface of the renderer.
+    /// Until the matching `end`, the camera viewport is the size of the target, so `clear_color` and
+    /// the letterbox cover the target.
+    pub fn begin_target(&mut self, sort_mode: SpriteSortMode, render_target: RenderTarget) {
+        self.begin(sort_mode);
+        self.render_target = Some((render_target, self.camera.viewport));
+        self.camera.viewport = render_target.dimensions();
+    }
+
     pub fn draw(&mut self, draw_data: DrawData) {
-        self.draw_data_cache.push(draw_data);
+        assert!(self.sort_mode.is_some(), "SpriteBatch::draw called before SpriteBatch::begin");
+        self.cache(None, draw_data);
+    }
+
+    /// Submits `draw_data` with `renderer` right away, together with everything drawn before it in the segment,
+    /// using the `draw_parameters` and `sampler_behaviour` set now.
+    ///
+    /// # Panics
+    /// Panics unless the segment was begun with `SpriteSortMode::Immediate`.
+    pub fn draw_immediate(&mut self, renderer: &mut dyn Renderer, draw_data: DrawData) -> Result<(), SpriteBatchError> {
+        assert!(
+            self.sort_mode == Some(SpriteSortMode::Immediate),
+            "SpriteBatch::draw_immediate called outside of a SpriteSortMode::Immediate segment"
+        );
+        self.cache(None, draw_data);
+        self.submit(renderer, SpriteSortMode::Immediate)
     }
 
-    pub fn flush(&mut self) -> Result<(), ()> {
-        if self.draw_data_cache.is_empty() {
-            return Ok(());
+    /// Draws `text` with its first line's top-left corner at `position`, as described by `Font::draw_data`.
+    /// `scale` multiplies the pixel size of the font. Glyphs the font creates are written into its atlas when the segment ends.
+    pub fn draw_text(&mut self, font: &dyn Font, text: &str, position: Vector2, color: Color, scale: f32) {
+        font.begin_segment(self.segment);
+        for draw_data in font.draw_data(text, position, color, scale) {
+            self.draw(draw_data);
         }
+        self.take_glyphs(font);
+    }
 
-        let mut vertices = vec![Vertex::default(); self.draw_data_cache.len() * 4].into_boxed_slice();
-        let mut indices = vec![0u32; self.draw_data_cache.len() * 6].into_boxed_slice();
+    /// Draws `layout`, which was laid out with `font`, with its top-left corner at `position`.
+    /// Its glyphs are looked up again, so a layout can be kept across frames even if the font evicts glyphs.
+    pub fn draw_text_layout(&mut self, font: &dyn Font, layout: &TextLayout, position: Vector2) {
+        font.begin_segment(self.segment);
+        for draw_data in layout.refresh_glyphs(font).draw_data(position) {
+            self.draw(draw_data);
+        }
+        self.take_glyphs(font);
+    }
 
-        let (screen_width, screen_height) = {
-            let screen_size = self.window.inner_size();
-            (screen_size.width as f32, screen_size.height as f32)
+    /// Draws `text` wrapped to the width of `bounds`, starting at its top-left corner. Lines below `bounds` are not clipped.
+    pub fn draw_text_box(&mut self, font: &dyn Font, text: &str, bounds: Rectangle, options: &TextOptions) {
+        font.begin_segment(self.segment);
+        let layout = TextLayout::new(font, text, &TextOptions { max_width: Some(bounds.width), ..options.clone() });
+        self.draw_text_layout(font, &layout, Vector2::new(bounds.position.x, bounds.top()));
+    }
+
+    /// Collects the glyph pixels `font` created for `end` to write, and the first error it ran into for `end` to return.
+    fn take_glyphs(&mut self, font: &dyn Font) {
+        self.render_target_writes.extend(font.take_writes());
+        if let Some(error) = font.take_error() {
+            self.font_error.get_or_insert(error);
+        }
+    }
+
+    /// Draws the quads of `nine_slice`, which stretch the sprite over its destination without distorting its borders.
+    pub fn draw_nine_slice(&mut self, nine_slice: &NineSlice) {
+        for draw_data in nine_slice.draw_data() {
+            self.draw(draw_data);
+        }
+    }
+
+    /// Draws the triangles of `shape` with the sprite, colour, depth, transform and blend mode of `draw_data`,
+    /// as described by `Mesh::push_shape`. Shapes share draw calls with the sprites around them.
+    pub fn draw_shape(&mut self, shape: Shape, draw_data: DrawData) {
+        assert!(self.sort_mode.is_some(), "SpriteBatch::draw_shape called before SpriteBatch::begin");
+        self.cache(Some(shape), draw_data);
+    }
+
+    pub fn draw_line(&mut self, start: Vector2, end: Vector2, thickness: f32, color: Color) {
+        self.draw_shape(Shape::line(start, end, thickness), DrawData { color, ..Default::default() });
+    }
+
+    pub fn draw_rectangle(&mut self, rectangle: Rectangle, color: Color) {
+        self.draw_shape(Shape::rectangle(rectangle), DrawData { color, ..Default::default() });
+    }
+
+    pub fn draw_rectangle_outline(&mut self, rectangle: Rectangle, thickness: f32, color: Color) {
+        self.draw_shape(Shape::rectangle_outline(rectangle, thickness), DrawData { color, ..Default::default() });
+    }
+
+    pub fn draw_circle(&mut self, center: Vector2, radius: f32, segments: u32, color: Color) {
+        self.draw_shape(Shape::circle(center, radius, segments), DrawData { color, ..Default::default() });
+    }
+
+    pub fn draw_polygon(&mut self, points: &[Vector2], color: Color) {
+        self.draw_shape(Shape::polygon(points), DrawData { color, ..Default::default() });
+    }
+
+    fn cache(&mut self, shape: Option<Shape>, draw_data: DrawData) {
+        self.draw_data_cache.push(
+            CachedDraw {
+                effect: self.effects.len() - 1,
+                clip: self.clips.last().copied(),
+                mask: self.mask,
+                stage: self.stage,
+                shape,
+                draw_data
+            }
+        );
+    }
+
+    /// Makes the sprites drawn from now on write the mask, or be limited to inside or outside of it.
+    /// The mask lives in the stencil buffer of the surface or render target, so it lasts until `Renderer::clear_mask`.
+    /// The mask mode stays set across segments, and sorting never moves sprites past a change of it.
+    pub fn set_mask(&mut self, mask: Option<MaskMode>) {
+        if mask != self.mask {
+            self.mask = mask;
+            self.stage += 1;
+        }
+    }
+
+    pub fn mask(&self) -> Option<MaskMode> {
+        self.mask
+    }
+
+    /// Cuts off the sprites drawn from now on at the edges of `clip`, a rectangle in world space that is
+    /// turned into a scissor rectangle on screen when the segment ends. Nested clips only leave their intersection visible.
+    /// Clips stay pushed across segments. Scissor rectangles are aligned to the screen, so when the camera is rotated
+    /// sprites are cut off at the bounding box of the rotated clip instead, which also shows some of them outside of it.
+    pub fn push_clip(&mut self, clip: Rectangle) {
+        let clip = match self.clips.last() {
+            Some(current) => current.intersection(&clip).unwrap_or(Rectangle::new(clip.position.x, clip.position.y, 0f32, 0f32)),
+            None => clip
         };
+        self.clips.push(clip);
+    }
 
-        for i in 0..self.draw_data_cache.len() {
-            let draw_data = &self.draw_data_cache[i];
-            let index = draw_data.sprite.index();
-            let max_sprite_size = Vector2::new(self.texture_array.dimensions().0 as f32, self.texture_array.dimensions().1 as f32);
-            let sprite_size = Vector2::new(draw_data.sprite.dimensions().0 as f32, draw_data.sprite.dimensions().1 as f32);
-            let source = draw_data.source.unwrap_or(Rectangle::new(0f32, 0f32, sprite_size.x, sprite_size.y));
-
-            let matrix = (
-                Matrix4x4::new_translation(draw_data.position.x / screen_width, draw_data.position.y / screen_height, 0f32)
-                * Matrix4x4::new_scaling(1f32 / screen_width, 1f32 / screen_height, 1f32)
-                * Matrix4x4::new_rotation(draw_data.rotation)
-                * Matrix4x4::new_scaling(draw_data.scale.x, draw_data.scale.y, 1f32)
-                * Matrix4x4::new_translation(-draw_data.origin.x, -draw_data.origin.y, 0f32)
-                * Matrix4x4::new_scaling(source.width, source.height, 1f32)
-            ).to_array();
-
-            let texture_coordinates_min = source.position / max_sprite_size;
-            let texture_coordinates_max = texture_coordinates_min + source.size() / max_sprite_size;
-            let color = [draw_data.color.red, draw_data.color.blue, draw_data.color.green, draw_data.color.alpha];
-
-            vertices[i * 4] = Vertex {
-                position: [0f32, 0f32, draw_data.depth],
-                uv: [texture_coordinates_min.x, texture_coordinates_min.y],
-                color,
-                index,
-                matrix
-            };
-            vertices[i * 4 + 1] = Vertex {
-                position: [1f32, 0f32, draw_data.depth],
-                uv: [texture_coordinates_max.x, texture_coordinates_min.y],
-                color,
-                index,
-                matrix
-            };
-            vertices[i * 4 + 2] = Vertex {
-                position: [1f32, 1f32, draw_data.depth],
-                uv: [texture_coordinates_max.x, texture_coordinates_max.y],
-                color,
-                index,
-                matrix
-            };
-            vertices[i * 4 + 3] = Vertex {
-                position: [0f32, 1f32, draw_data.depth],
-                uv: [texture_coordinates_min.x, texture_coordinates_max.y],
-                color,
-                index,
-                matrix
-            };
+    /// Removes the clip rectangle pushed last.
+    pub fn pop_clip(&mut self) {
+        assert!(self.clips.pop().is_some(), "SpriteBatch::pop_clip called without SpriteBatch::push_clip");
+    }
 
-            indices[i * 6] = i as u32 * 4;
-            indices[i * 6 + 1] = i as u32 * 4 + 1;
-            indices[i * 6 + 2] = i as u32 * 4 + 2;
-            indices[i * 6 + 3] = i as u32 * 4 + 2;
-            indices[i * 6 + 4] = i as u32 * 4 + 3;
-            indices[i * 6 + 5] = i as u32 * 4;
+    /// Shades the sprites drawn from now on with `effect`, or with the default shader if it is `None`.
+    /// It stays set across segments, and sprites with different effects are submitted with separate draw calls.
+    pub fn set_effect(&mut self, effect: Option<Effect>) {
+        let current = self.effects.len() - 1;
+        if self.draw_data_cache.iter().any(|draw| draw.effect == current) {
+            self.effects.push(effect);
+        } else {
+            self.effects[current] = effect;
         }
+    }
 
-        self.draw_data_cache.clear();
+    pub fn effect(&self) -> Option<&Effect> {
+        self.effects.last().and_then(Option::as_ref)
+    }
 
-        let Ok(vertex_buffer) = VertexBuffer::new(self.display.as_ref(), &vertices) else {
-            return Err(());
+    /// Ends the current batch segment and draws it with `renderer`.
+    /// The surface is neither cleared nor finished, so several segments can be drawn onto the same frame.
+    /// Consecutive sprites and shapes sampling the same texture, either the texture array or a render target, with the same effect
+    /// blend mode, clip and mask mode share a draw call. The segment ends even if drawing it fails, dropping what was not drawn.
+    pub fn end(&mut self, renderer: &mut dyn Renderer) -> Result<(), SpriteBatchError> {
+        let Some(sort_mode) = self.sort_mode.take() else {
+            panic!("SpriteBatch::end called before SpriteBatch::begin");
         };
-        
-        let Ok(index_buffer) = IndexBuffer::new(self.display.as_ref(), PrimitiveType::TrianglesList, &indices) else {
-            return Err(());
+
+        let result = self.submit(renderer, sort_mode);
+        if let Some((_, viewport)) = self.render_target.take() {
+            self.camera.viewport = viewport;
+        }
+        result
+    }
+
+    /// Draws everything cached so far in the segment with `renderer`, after writing the pending render target writes.
+    /// The cache and writes are taken even if this fails, so they never leak into the next submission. If a write fails,
+    /// nothing is drawn. A font error is only returned once nothing else failed, and is kept for the next submission otherwise.
+    fn submit(&mut self, renderer: &mut dyn Renderer, sort_mode: SpriteSortMode) -> Result<(), SpriteBatchError> {
+        let camera = self.camera;
+        let render_target = self.render_target.map(|(render_target, _)| render_target);
+
+        self.stage = 0;
+        let current_effect = self.effects.last().cloned().flatten();
+        let effects = std::mem::replace(&mut self.effects, vec![current_effect]);
+        let mut draws = std::mem::take(&mut self.draw_data_cache);
+        let mut result = self.render_target_writes.drain(..).try_for_each(
+            |write| renderer.write_render_target(write.render_target, write.position, &write.image)
+        );
+        if result.is_err() {
+            draws.clear();
+        }
+
+        sort_mode.sort(&mut draws);
+
+        let projection = camera.projection();
+        let draw_parameters = DrawParameters { 
+            viewport: self.draw_parameters.viewport.or(Some(camera.letterbox())), 
+            ..self.draw_parameters.clone() 
         };
-        
-        let mut frame = self.display.draw();
-        frame.clear_all((0f32, 0f32, 0f32, 0f32), 0f32, 0i32);
-        frame.draw(
-            &vertex_buffer,
-            &index_buffer,
-            &self.program,
-            &uniform! {
-                textures: Sampler(&self.texture_array, self.sampler_behaviour)
-            },
-            &self.draw_parameters
-        ).map_err(|_| ())?;
-        
-        frame.finish().map_err(|_| ())
-    }
-
-}
\ No newline at end of file
+        let group_size = if sort_mode == SpriteSortMode::Immediate { 1 } else { usize::MAX };
+        let mut remaining = &draws[..];
+        while let Some(first) = remaining.first() {
+            let texture = first.draw_data.sprite.render_target();
+            let effect = effects[first.effect].as_ref();
+            let blend_mode = first.draw_data.blend_mode;
+            let length = remaining.iter()
+                .take(group_size)
+                .take_while(
+                    |draw| {
+                        draw.draw_data.sprite.render_target() == texture
+                            && effects[draw.effect].as_ref() == effect
+                            && draw.draw_data.blend_mode == blend_mode
+                            && draw.clip == first.clip
+                            && draw.mask == first.mask
+                    }
+                )
+                .count();
+            let (group, rest) = remaining.split_at(length);
+            remaining = rest;
+
+            let texture_dimensions = texture.map_or(renderer.texture_dimensions(), |texture| texture.dimensions());
+            let mut mesh = Mesh::new();
+            for draw in group {
+                match &draw.shape {
+                    Some(shape) => mesh.push_shape(shape, &draw.draw_data, projection, texture_dimensions),
+                    None => mesh.push_sprite(&draw.draw_data, projection, texture_dimensions)
+                }
+            }
+
+            let writes_mask = matches!(first.mask, Some(MaskMode::Write { .. }));
+            let draw_call = DrawCall {
+                texture,
+                target: render_target,
+                effect,
+                alpha_cutoff: match first.mask {
+                    Some(MaskMode::Write { alpha_cutoff }) => alpha_cutoff,
+                    _ => 0f32
+                },
+                draw_parameters: DrawParameters {
+                    blend: blend_mode.map_or(draw_parameters.blend, |blend_mode| blend_mode.blend()),
+                    scissor: first.clip.map(|clip| scissor(&camera, clip)).or(draw_parameters.scissor),
+                    stencil: first.mask.map_or(draw_parameters.stencil, |mask| mask.stencil()),
+                    color_mask: if writes_mask { (false, false, false, false) } else { draw_parameters.color_mask },
+                    ..draw_parameters.clone()
+                },
+                sampler_behaviour: self.sampler_behaviour
+            };
+            result = renderer.draw(&mesh, &draw_call);
+            if result.is_err() {
+                break;
+            }
+        }
+
+        draws.clear();
+        self.draw_data_cache = draws;
+        result.and_then(|()| self.font_error.take().map_or(Ok(()), |error| Err(SpriteBatchError::Font(error))))
+    }
+}
+
+/// The pixels of the surface covered by a world space rectangle, from its bottom-left corner.
+fn scissor(camera: &Camera2D, clip: Rectangle) -> Rect {
+    let corners = [
+        clip.position,
+        Vector2::new(clip.right(), clip.position.y),
+        Vector2::new(clip.right(), clip.top()),
+        Vector2::new(clip.position.x, clip.top())
+    ].map(|corner| camera.world_to_screen(corner));
+
+    let left = corners.iter().map(|corner| corner.x).fold(f32::INFINITY, f32::min).round().max(0f32);
+    let right = corners.iter().map(|corner| corner.x).fold(f32::NEG_INFINITY, f32::max).round().max(left);
+    let top = corners.iter().map(|corner| corner.y).fold(f32::INFINITY, f32::min).round().max(0f32);
+    let bottom = corners.iter().map(|corner| corner.y).fold(f32::NEG_INFINITY, f32::max).round().max(top);
+    let viewport_height = camera.viewport.1 as f32;
+    Rect {
+        left: left as u32,
+        bottom: (viewport_height - bottom).max(0f32) as u32,
+        width: (right - left) as u32,
+        height: (bottom - top) as u32
+    }
+}
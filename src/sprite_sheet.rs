@@ -0,0 +1,142 @@
+use defaults::Defaults;
+
+use crate::{math::Rectangle, sprite::{Sprite, SpriteError}, sprite_batch::DrawData};
+
+/// A part of a sprite, drawn as if it were a sprite of its own.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureRegion {
+    pub sprite: Sprite,
+    /// The part of `sprite` covered by the region, from its bottom-left corner.
+    pub source: Rectangle
+}
+
+impl TextureRegion {
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.source.width as u32, self.source.height as u32)
+    }
+
+    /// Draw data showing the region, to be completed with struct update syntax:
+    /// `DrawData { position, ..region.draw_data() }`.
+    pub fn draw_data(&self) -> DrawData {
+        DrawData { sprite: self.sprite, source: Some(self.source), ..Default::default() }
+    }
+}
+
+impl From<Sprite> for TextureRegion {
+    fn from(sprite: Sprite) -> Self {
+        let (width, height) = sprite.dimensions();
+        Self { sprite, source: Rectangle::new(0f32, 0f32, width as f32, height as f32) }
+    }
+}
+
+/// How the cells of a sprite sheet are laid out, in pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Defaults)]
+pub struct Grid {
+    #[def = "(1u32, 1u32)"]
+    pub cell_dimensions: (u32, u32),
+    /// The space between the edges of the image and the outer cells.
+    pub margin: u32,
+    /// The space between neighbouring cells.
+    pub spacing: u32,
+    /// How many columns to slice, or `None` for as many as fit.
+    pub columns: Option<u32>,
+    /// How many rows to slice, or `None` for as many as fit.
+    pub rows: Option<u32>
+}
+
+impl Grid {
+    /// A grid of as many `cell_dimensions` cells as fit, without margin or spacing.
+    pub fn new(cell_dimensions: (u32, u32)) -> Self {
+        Self { cell_dimensions, ..Default::default() }
+    }
+}
+
+/// A sprite sliced into a grid of equally sized regions, numbered row by row from the top-left cell,
+/// the way sheets are laid out in image editors.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpriteSheet {
+    sprite: Sprite,
+    columns: u32,
+    rows: u32,
+    regions: Vec<TextureRegion>
+}
+
+impl SpriteSheet {
+    /// # Panics
+    /// Panics if a cell dimension is zero, or if `grid` asks for more columns or rows than fit in the sprite.
+    pub fn new(sprite: Sprite, grid: &Grid) -> Self {
+        Self::try_new(sprite, grid).unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Like `new`, but returns `SpriteError::InvalidGrid` instead of panicking.
+    pub fn try_new(sprite: Sprite, grid: &Grid) -> Result<Self, SpriteError> {
+        let (width, height) = sprite.dimensions();
+        let (cell_width, cell_height) = grid.cell_dimensions;
+        let invalid_grid = || SpriteError::InvalidGrid { grid: *grid, dimensions: sprite.dimensions() };
+        if cell_width == 0 || cell_height == 0 {
+            return Err(invalid_grid());
+        }
+
+        let fitting = |length: u32, cell: u32| (length.saturating_sub(2 * grid.margin) + grid.spacing) / (cell + grid.spacing);
+        let (fitting_columns, fitting_rows) = (fitting(width, cell_width), fitting(height, cell_height));
+        let columns = grid.columns.unwrap_or(fitting_columns);
+        let rows = grid.rows.unwrap_or(fitting_rows);
+        if columns > fitting_columns || rows > fitting_rows {
+            return Err(invalid_grid());
+        }
+
+        let mut regions = Vec::with_capacity((columns * rows) as usize);
+        for row in 0..rows {
+            let top = height - grid.margin - row * (cell_height + grid.spacing);
+            for column in 0..columns {
+                let left = grid.margin + column * (cell_width + grid.spacing);
+                regions.push(
+                    TextureRegion {
+                        sprite,
+                        source: Rectangle::new(left as f32, (top - cell_height) as f32, cell_width as f32, cell_height as f32)
+                    }
+                );
+            }
+        }
+
+        Ok(Self { sprite, columns, rows, regions })
+    }
+
+    pub fn sprite(&self) -> Sprite {
+        self.sprite
+    }
+
+    pub fn columns(&self) -> u32 {
+        self.columns
+    }
+
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    /// The region at `index`, counting row by row from the top-left cell.
+    pub fn get(&self, index: usize) -> Option<TextureRegion> {
+        self.regions.get(index).copied()
+    }
+
+    /// The region in `column` and `row`, counting from the top-left cell.
+    pub fn cell(&self, column: u32, row: u32) -> Option<TextureRegion> {
+        if column < self.columns && row < self.rows {
+            self.get((row * self.columns + column) as usize)
+        } else {
+            None
+        }
+    }
+
+    pub fn regions(&self) -> &[TextureRegion] {
+        &self.regions
+    }
+}
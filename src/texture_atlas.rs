@@ -0,0 +1,274 @@
+use std::{error::Error, f32::consts::FRAC_PI_2, fmt::{self, Formatter}, fs, io, path::Path};
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    math::{Rectangle, Vector2},
+    sprite::{Sprite, SpriteError, SpriteLoader},
+    sprite_batch::DrawData,
+    sprite_sheet::TextureRegion
+};
+
+#[derive(Debug)]
+pub enum AtlasError {
+    Io(io::Error),
+    /// The metadata is not valid TexturePacker or Aseprite JSON.
+    Json(serde_json::Error),
+    /// The sheet image could not be loaded.
+    Sprite(SpriteError)
+}
+
+impl fmt::Display for AtlasError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "failed to read the atlas: {error}"),
+            Self::Json(error) => write!(f, "failed to parse the atlas: {error}"),
+            Self::Sprite(error) => write!(f, "failed to load the atlas image: {error}")
+        }
+    }
+}
+
+impl Error for AtlasError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Json(error) => Some(error),
+            Self::Sprite(error) => Some(error)
+        }
+    }
+}
+
+/// A named sprite packed into a texture atlas, possibly trimmed of its transparent borders and rotated.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub name: String,
+    /// The pixels of the frame in the sheet. If `rotated`, they are turned a quarter clockwise,
+    /// so the region is as wide as the frame is tall.
+    pub region: TextureRegion,
+    pub rotated: bool,
+    /// From the bottom-left corner of the untrimmed sprite to the bottom-left corner of the trimmed pixels.
+    pub offset: Vector2,
+    /// The dimensions of the sprite before it was trimmed.
+    pub source_dimensions: (u32, u32),
+    /// The point the sprite is placed and rotated by, as a fraction of its untrimmed dimensions from its bottom-left corner.
+    /// The centre unless the metadata gives another pivot.
+    pub pivot: Vector2,
+    /// How long the frame is shown in an animation, in seconds, if the metadata gives it.
+    pub duration: Option<f32>
+}
+
+impl Frame {
+    /// Whether transparent borders were removed from the sprite when it was packed.
+    pub fn trimmed(&self) -> bool {
+        self.region_dimensions() != self.source_dimensions
+    }
+
+    /// Draw data showing the frame upright with its pivot at the position, to be completed with struct update syntax.
+    /// Rotated frames are drawn turned back a quarter, so an extra `rotation` is added to that.
+    pub fn draw_data(&self) -> DrawData {
+        let (width, height) = self.source_dimensions;
+        let origin = Vector2::new(self.pivot.x * width as f32, self.pivot.y * height as f32) - self.offset;
+        let (origin, rotation) = if self.rotated {
+            (Vector2::new(origin.y, self.region_dimensions().0 as f32 - origin.x), FRAC_PI_2)
+        } else {
+            (origin, 0f32)
+        };
+
+        DrawData { origin, rotation, ..self.region.draw_data() }
+    }
+
+    /// The dimensions of the trimmed sprite, upright.
+    fn region_dimensions(&self) -> (u32, u32) {
+        let (width, height) = self.region.dimensions();
+        if self.rotated { (height, width) } else { (width, height) }
+    }
+}
+
+/// How an animation plays the frames of a tag.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TagDirection {
+    #[default]
+    Forward,
+    Reverse,
+    /// Forward then backward.
+    PingPong,
+    /// Backward then forward.
+    PingPongReverse
+}
+
+/// A named range of frames, such as an animation exported by Aseprite.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrameTag {
+    pub name: String,
+    /// The indices of the first and last frames of the tag, inclusive.
+    pub from: usize,
+    pub to: usize,
+    pub direction: TagDirection,
+    /// How many times the tag plays, or `None` to loop forever.
+    pub repeat: Option<u32>
+}
+
+/// The frames of a sheet packed by TexturePacker or exported by Aseprite, in the JSON hash or JSON array format.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextureAtlas {
+    sprite: Sprite,
+    frames: Vec<Frame>,
+    tags: Vec<FrameTag>
+}
+
+impl TextureAtlas {
+    /// Reads a JSON file and loads its image, found next to it, as a watched sprite file.
+    pub fn load_file(path: impl AsRef<Path>, sprite_loader: &mut SpriteLoader) -> Result<Self, AtlasError> {
+        let path = path.as_ref();
+        let data = fs::read(path).map_err(AtlasError::Io)?;
+        let directory = path.parent().unwrap_or(Path::new(""));
+        Self::from_json(&data, |image| sprite_loader.load_sprite_file(directory.join(image)).map_err(AtlasError::Sprite))
+    }
+
+    /// Parses JSON metadata, calling `load_image` with the file name of the sheet image to get its sprite.
+    /// The image is only loaded once all the metadata is parsed, so invalid metadata never loads it.
+    pub fn from_json(data: &[u8], load_image: impl FnOnce(&str) -> Result<Sprite, AtlasError>) -> Result<Self, AtlasError> {
+        let document: Document = serde_json::from_slice(data).map_err(AtlasError::Json)?;
+
+        // Hashes are keyed by the frame names, while arrays name every frame by its `filename`.
+        let frames: Vec<(String, Value)> = match document.frames {
+            Value::Object(frames) => frames.into_iter().collect(),
+            Value::Array(frames) => frames.into_iter().map(
+                |frame| {
+                    let name = frame.get("filename").and_then(Value::as_str).unwrap_or_default().to_owned();
+                    (name, frame)
+                }
+            ).collect(),
+            _ => return Err(AtlasError::Json(serde::de::Error::custom("`frames` is neither an object nor an array")))
+        };
+
+        let frames = frames.into_iter().map(
+            |(name, frame)| serde_json::from_value(frame).map(|frame: FrameData| (name, frame)).map_err(AtlasError::Json)
+        ).collect::<Result<Vec<_>, AtlasError>>()?;
+
+        let tags = document.meta.frame_tags.into_iter().map(
+            |tag| FrameTag {
+                name: tag.name,
+                from: tag.from,
+                to: tag.to,
+                direction: match tag.direction.as_str() {
+                    "reverse" => TagDirection::Reverse,
+                    "pingpong" => TagDirection::PingPong,
+                    "pingpong_reverse" => TagDirection::PingPongReverse,
+                    _ => TagDirection::Forward
+                },
+                repeat: tag.repeat.and_then(|repeat| repeat.parse().ok()).filter(|repeat| *repeat > 0)
+            }
+        ).collect();
+
+        let sprite = load_image(&document.meta.image)?;
+        let sheet_height = sprite.dimensions().1 as f32;
+        let frames = frames.into_iter().map(
+            |(name, frame)| {
+                let (width, height) = if frame.rotated { (frame.frame.h, frame.frame.w) } else { (frame.frame.w, frame.frame.h) };
+                let trimmed = frame.sprite_source_size.unwrap_or(Bounds { x: 0f32, y: 0f32, w: frame.frame.w, h: frame.frame.h });
+                let source_size = frame.source_size.unwrap_or(Size { w: trimmed.w, h: trimmed.h });
+
+                Frame {
+                    name,
+                    region: sprite.region(Rectangle::new(frame.frame.x, sheet_height - frame.frame.y - height, width, height)),
+                    rotated: frame.rotated,
+                    offset: Vector2::new(trimmed.x, source_size.h - trimmed.y - trimmed.h),
+                    source_dimensions: (source_size.w as u32, source_size.h as u32),
+                    pivot: frame.pivot.map_or(Vector2::new(0.5, 0.5), |pivot| Vector2::new(pivot.x, 1f32 - pivot.y)),
+                    duration: frame.duration.map(|duration| duration / 1000f32)
+                }
+            }
+        ).collect();
+
+        Ok(Self { sprite, frames, tags })
+    }
+
+    /// The sheet image.
+    pub fn sprite(&self) -> Sprite {
+        self.sprite
+    }
+
+    /// The frames in the order of the file.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn frame(&self, name: &str) -> Option<&Frame> {
+        self.frames.iter().find(|frame| frame.name == name)
+    }
+
+    pub fn tags(&self) -> &[FrameTag] {
+        &self.tags
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&FrameTag> {
+        self.tags.iter().find(|tag| tag.name == name)
+    }
+
+    /// The frames of the tag called `name`, in the order of the file regardless of its direction.
+    pub fn tag_frames(&self, name: &str) -> Option<&[Frame]> {
+        self.tag(name).and_then(|tag| self.frames.get(tag.from..=tag.to))
+    }
+}
+
+#[derive(Deserialize)]
+struct Document {
+    frames: Value,
+    meta: Meta
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Meta {
+    image: String,
+    #[serde(default)]
+    frame_tags: Vec<TagData>
+}
+
+#[derive(Deserialize)]
+struct TagData {
+    name: String,
+    from: usize,
+    to: usize,
+    #[serde(default)]
+    direction: String,
+    /// Aseprite writes the repeat count as a string.
+    repeat: Option<String>
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FrameData {
+    /// The packed pixels, with the width and height of the upright sprite even if it is rotated.
+    frame: Bounds,
+    #[serde(default)]
+    rotated: bool,
+    sprite_source_size: Option<Bounds>,
+    source_size: Option<Size>,
+    pivot: Option<Point>,
+    /// In milliseconds.
+    duration: Option<f32>
+}
+
+/// A rectangle from the top-left corner of an image, with y pointing down.
+#[derive(Clone, Copy, Deserialize)]
+struct Bounds {
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32
+}
+
+#[derive(Clone, Copy, Deserialize)]
+struct Size {
+    w: f32,
+    h: f32
+}
+
+#[derive(Clone, Copy, Deserialize)]
+struct Point {
+    x: f32,
+    y: f32
+}
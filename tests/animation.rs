@@ -0,0 +1,198 @@
+use std::{cell::RefCell, rc::Rc};
+use sprite_batching::{
+    Animation,
+    AnimationFrame,
+    AnimationPlayer,
+    DrawData,
+    Grid,
+    LoopMode,
+    SpriteLoader,
+    TextureAtlas,
+    TextureRegion,
+    image::RgbaImage,
+    math::Vector2
+};
+
+/// The three 16 by 16 frames of a vertical strip, from the top.
+fn regions() -> Vec<TextureRegion> {
+    let mut sprite_loader = SpriteLoader::new();
+    let sprite = sprite_loader.load_sprite(RgbaImage::new(16, 48));
+    sprite.slice(&Grid::new((16, 16))).regions().to_vec()
+}
+
+fn player(mode: LoopMode) -> AnimationPlayer {
+    let mut player = AnimationPlayer::new();
+    player.add_clip("clip", Animation::from_regions(regions(), 0.25, mode));
+    player.play("clip");
+    player
+}
+
+/// The frame shown after each of `updates` quarter seconds.
+fn frames_shown(player: &mut AnimationPlayer, updates: usize) -> Vec<usize> {
+    (0..updates).map(
+        |_| {
+            player.update(0.25);
+            player.frame_index().unwrap()
+        }
+    ).collect()
+}
+
+#[test]
+fn loop_modes_order_the_frames() {
+    assert_eq!(frames_shown(&mut player(LoopMode::Loop), 5), vec![1, 2, 0, 1, 2]);
+    assert_eq!(frames_shown(&mut player(LoopMode::PingPong), 6), vec![1, 2, 1, 0, 1, 2]);
+    assert_eq!(frames_shown(&mut player(LoopMode::Reverse), 4), vec![1, 0, 2, 1]);
+
+    let mut once = player(LoopMode::Once);
+    assert_eq!(frames_shown(&mut once, 2), vec![1, 2]);
+    assert!(!once.is_finished());
+    assert_eq!(frames_shown(&mut once, 2), vec![2, 2]);
+    assert!(once.is_finished());
+
+    assert_eq!(Animation::from_regions(regions(), 0.25, LoopMode::PingPong).duration(), 1f32);
+}
+
+#[test]
+fn playback_is_deterministic_and_scaled_by_speed() {
+    let regions = regions();
+    let animation = Animation::new(
+        vec![AnimationFrame::new(regions[0], 0.5), AnimationFrame::new(regions[1], 0.1), AnimationFrame::new(regions[2], 0.3)],
+        LoopMode::Loop
+    );
+    let delta_times = [0.05, 0.2, 0.3, 0f32, 0.12, 0.2, 1f32, 0.07];
+    let run = |speed: f32| {
+        let mut player = AnimationPlayer::new();
+        player.add_clip("clip", animation.clone());
+        player.play("clip");
+        player.speed = speed;
+        delta_times.iter().map(
+            |delta_time| {
+                player.update(*delta_time);
+                player.frame_index().unwrap()
+            }
+        ).collect::<Vec<_>>()
+    };
+
+    assert_eq!(run(1f32), run(1f32));
+    assert_eq!(run(1f32), vec![0, 0, 1, 1, 2, 2, 0, 0]);
+    assert_eq!(run(1.5), vec![0, 0, 2, 2, 0, 0, 0, 0]);
+    assert_eq!(run(0f32), vec![0; delta_times.len()]);
+}
+
+#[test]
+fn long_updates_skip_whole_passes() {
+    let mut skipped = player(LoopMode::PingPong);
+    skipped.update(1e6 + 0.5);
+    let mut stepped = player(LoopMode::PingPong);
+    stepped.update(0.5);
+    assert_eq!(skipped.frame_index(), stepped.frame_index());
+
+    let mut once = player(LoopMode::Once);
+    once.update(f32::MAX);
+    assert!(once.is_finished());
+    assert_eq!(once.frame_index(), Some(2));
+}
+
+#[test]
+#[should_panic]
+fn infinite_updates_are_rejected() {
+    player(LoopMode::Loop).update(f32::INFINITY);
+}
+
+#[test]
+fn named_frames_fire_their_callbacks() {
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let mut player = AnimationPlayer::new();
+    player.add_clip("walk", Animation::from_regions(regions(), 0.25, LoopMode::Loop).with_event(0, "step").with_event(2, "land"));
+    for name in ["step", "land"] {
+        let events = events.clone();
+        player.on_event(name, move |event| events.borrow_mut().push((event.name.clone(), event.clip.clone(), event.frame)));
+    }
+
+    player.play("walk");
+    player.update(0f32);
+    assert_eq!(events.borrow().len(), 1);
+
+    // Every frame shown is reported, even when one update skips over several of them.
+    player.update(0.5);
+    player.update(0.25);
+    player.play("walk");
+    player.update(0.25);
+    assert_eq!(
+        *events.borrow(),
+        vec![("step".to_owned(), "walk".to_owned(), 0), ("land".to_owned(), "walk".to_owned(), 2), ("step".to_owned(), "walk".to_owned(), 0)]
+    );
+
+    // Replacing the clip restarts it, so its first frame fires its event again.
+    events.borrow_mut().clear();
+    player.add_clip("walk", Animation::from_regions(regions(), 0.25, LoopMode::Loop).with_event(0, "step"));
+    player.update(0f32);
+    assert_eq!(*events.borrow(), vec![("step".to_owned(), "walk".to_owned(), 0)]);
+}
+
+#[test]
+fn crossfades_draw_the_new_clip_over_the_old_one() {
+    let regions = regions();
+    let mut player = AnimationPlayer::new();
+    player.add_clip("idle", Animation::from_regions([regions[0]], 1f32, LoopMode::Loop));
+    player.add_clip("run", Animation::from_regions([regions[1], regions[2]], 1f32, LoopMode::Loop));
+    player.play("idle");
+
+    let base = DrawData { position: Vector2::new(5f32, 6f32), origin: Vector2::ONE * 8f32, ..Default::default() };
+    let draw_data = player.draw_data(&base);
+    assert_eq!(draw_data.len(), 1);
+    assert_eq!((draw_data[0].sprite, draw_data[0].source), (regions[0].sprite, Some(regions[0].source)));
+    assert_eq!((draw_data[0].position, draw_data[0].origin), (base.position, base.origin));
+
+    player.crossfade("run", 0.5);
+    player.update(0.25);
+    assert_eq!(player.current_clip(), Some("run"));
+    let draw_data = player.draw_data(&base);
+    let drawn: Vec<_> = draw_data.iter().map(|draw_data| (draw_data.source, draw_data.color.alpha)).collect();
+    assert_eq!(drawn, vec![(Some(regions[0].source), 1f32), (Some(regions[1].source), 0.5)]);
+
+    // Playing the clip that is already playing neither restarts it nor stops the crossfade.
+    player.play("run");
+    player.update(0.25);
+    assert_eq!(player.crossfade_weight(), 1f32);
+    assert_eq!(player.draw_data(&base).len(), 1);
+    player.update(0.5);
+    assert_eq!(player.frame_index(), Some(1));
+}
+
+#[test]
+fn atlas_tags_become_animations() {
+    let json = r#"{"frames": [
+        {"filename": "0", "frame": {"x":0,"y":0,"w":16,"h":16}, "duration": 100},
+        {"filename": "1", "frame": {"x":16,"y":0,"w":16,"h":16}, "duration": 200},
+        {"filename": "2", "frame": {"x":32,"y":0,"w":16,"h":16}},
+        {"filename": "3", "frame": {"x":0,"y":0,"w":16,"h":16}, "duration": 0},
+        {"filename": "4", "frame": {"x":16,"y":0,"w":16,"h":16}, "duration": -50}
+    ],
+    "meta": {"image": "sheet.png", "frameTags": [
+        {"name": "fall", "from": 0, "to": 2, "direction": "reverse", "repeat": "1"},
+        {"name": "bounce", "from": 0, "to": 1, "direction": "pingpong"},
+        {"name": "blink", "from": 0, "to": 1, "direction": "forward", "repeat": "3"},
+        {"name": "wobble", "from": 0, "to": 2, "direction": "pingpong", "repeat": "1"},
+        {"name": "flicker", "from": 2, "to": 4}
+    ]}}"#;
+    let mut sprite_loader = SpriteLoader::new();
+    let atlas = TextureAtlas::from_json(json.as_bytes(), |_| Ok(sprite_loader.load_sprite(RgbaImage::new(48, 16)))).unwrap();
+
+    let fall = Animation::from_atlas_tag(&atlas, "fall").unwrap();
+    assert_eq!(fall.mode, LoopMode::Once);
+    assert_eq!(fall.frames().iter().map(|frame| frame.duration).collect::<Vec<_>>(), vec![0.1, 0.2, 0.1]);
+    assert_eq!(fall.frames()[0].region, atlas.frames()[2].region);
+    assert_eq!(fall.frames()[0].origin, Some(Vector2::new(8f32, 8f32)));
+
+    assert_eq!(Animation::from_atlas_tag(&atlas, "bounce").unwrap().mode, LoopMode::PingPong);
+
+    // Only a single pass of a forward or reverse tag can stop; other repeat counts loop forever.
+    assert_eq!(Animation::from_atlas_tag(&atlas, "blink").unwrap().mode, LoopMode::Loop);
+    assert_eq!(Animation::from_atlas_tag(&atlas, "wobble").unwrap().mode, LoopMode::PingPong);
+    assert!(Animation::from_atlas_tag(&atlas, "jump").is_none());
+
+    // Frames exported without a positive duration are shown like those without one.
+    let flicker = Animation::from_atlas_tag(&atlas, "flicker").unwrap();
+    assert_eq!(flicker.frames().iter().map(|frame| frame.duration).collect::<Vec<_>>(), vec![0.1, 0.1, 0.1]);
+}
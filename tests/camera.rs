@@ -0,0 +1,40 @@
+use std::f32::consts::FRAC_PI_2;
+use sprite_batching::{Camera2D, VirtualResolution, glium::Rect, math::Vector2};
+
+fn assert_near(actual: Vector2, expected: Vector2) {
+    assert!(actual.distance(&expected) < 1e-3, "{actual:?} != {expected:?}");
+}
+
+#[test]
+fn screen_corners_map_to_the_visible_world_area() {
+    let camera = Camera2D { position: Vector2::new(100f32, 50f32), zoom: 2f32, ..Camera2D::new((200, 100)) };
+
+    assert_near(camera.screen_to_world(Vector2::new(100f32, 50f32)), Vector2::new(100f32, 50f32));
+    assert_near(camera.screen_to_world(Vector2::ZERO), Vector2::new(50f32, 75f32));
+    assert_near(camera.screen_to_world(Vector2::new(200f32, 100f32)), Vector2::new(150f32, 25f32));
+    assert_near(camera.world_to_screen(Vector2::new(50f32, 75f32)), Vector2::ZERO);
+}
+
+#[test]
+fn rotated_camera_round_trips_between_screen_and_world() {
+    let camera = Camera2D { position: Vector2::new(-3f32, 7f32), zoom: 0.5, rotation: FRAC_PI_2, ..Camera2D::new((640, 480)) };
+
+    // Turning the view counterclockwise makes the world's up direction point to the screen's right.
+    assert_near(camera.world_to_screen(camera.position + Vector2::UNIT_Y), Vector2::new(320.5, 240f32));
+    for screen_position in [Vector2::ZERO, Vector2::new(640f32, 0f32), Vector2::new(123f32, 456f32)] {
+        assert_near(camera.world_to_screen(camera.screen_to_world(screen_position)), screen_position);
+    }
+}
+
+#[test]
+fn virtual_resolution_is_letterboxed_with_integer_scaling() {
+    let camera = Camera2D {
+        virtual_resolution: Some(VirtualResolution::new(320, 180, true)),
+        ..Camera2D::new((1000, 700))
+    };
+
+    assert_eq!(camera.letterbox(), Rect { left: 20, bottom: 80, width: 960, height: 540 });
+    assert_near(camera.screen_to_world(Vector2::new(20f32, 80f32)), Vector2::new(-160f32, 90f32));
+    assert_near(camera.world_to_screen(Vector2::new(160f32, -90f32)), Vector2::new(980f32, 620f32));
+    assert_near(camera.visible_size(), Vector2::new(320f32, 180f32));
+}
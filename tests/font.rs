@@ -0,0 +1,313 @@
+use std::{fs, path::PathBuf};
+use sprite_batching::{
+    ApplicationContext,
+    BitmapFont,
+    Color,
+    DrawData,
+    Font,
+    FontError,
+    HeadlessApplication,
+    Renderer,
+    SoftwareRenderer,
+    SpriteBatch,
+    SpriteBatchError,
+    SpriteLoader,
+    SpriteSortMode,
+    TrueTypeFont,
+    glium::Blend,
+    image::{Rgba, RgbaImage},
+    math::{Rectangle, Vector2}
+};
+
+const TEXT_DESCRIPTOR: &str = r#"info face="Test Font" size=8 bold=0 italic=0 charset="" unicode=1 stretchH=100 smooth=0 aa=1 padding=0,0,0,0 spacing=1,1
+common lineHeight=10 base=8 scaleW=32 scaleH=16 pages=1 packed=0
+page id=0 file="test font.png"
+chars count=3
+char id=65   x=0     y=0     width=6     height=8     xoffset=0     yoffset=0     xadvance=7     page=0  chnl=15
+char id=86   x=8     y=4     width=5     height=6     xoffset=1     yoffset=2     xadvance=6     page=0  chnl=15
+char id=32   x=0     y=0     width=0     height=0     xoffset=0     yoffset=8     xadvance=3     page=0  chnl=15
+kernings count=1
+kerning first=65  second=86  amount=-2
+"#;
+
+/// The same font as `TEXT_DESCRIPTOR`, in the binary format.
+fn binary_descriptor() -> Vec<u8> {
+    let mut data = b"BMF\x03".to_vec();
+    let mut block = |block_type: u8, contents: Vec<u8>| {
+        data.push(block_type);
+        data.extend((contents.len() as u32).to_le_bytes());
+        data.extend(contents);
+    };
+
+    block(1, [&8i16.to_le_bytes()[..], &[0, 0], &100u16.to_le_bytes(), &[1, 0, 0, 0, 0, 1, 1, 0], b"Test Font\0"].concat());
+    block(2, [10u16, 8, 32, 16, 1].iter().flat_map(|value| value.to_le_bytes()).chain([0, 0, 0, 0, 0]).collect());
+    block(3, b"test font.png\0".to_vec());
+    block(
+        4,
+        [(65u32, [0u16, 0, 6, 8], [0i16, 0, 7]), (86, [8, 4, 5, 6], [1, 2, 6]), (32, [0, 0, 0, 0], [0, 8, 3])].iter().flat_map(
+            |(id, rectangle, placement)| id.to_le_bytes().into_iter()
+                .chain(rectangle.iter().flat_map(|value| value.to_le_bytes()))
+                .chain(placement.iter().flat_map(|value| value.to_le_bytes()))
+                .chain([0, 15])
+                .collect::<Vec<u8>>()
+        ).collect()
+    );
+    block(5, [&65u32.to_le_bytes()[..], &86u32.to_le_bytes(), &(-2i16).to_le_bytes()].concat());
+    data
+}
+
+fn load(data: &[u8]) -> (BitmapFont, SpriteLoader) {
+    let mut sprite_loader = SpriteLoader::new();
+    let font = BitmapFont::from_bytes(
+        data,
+        |file| {
+            assert_eq!(file, "test font.png");
+            Ok(sprite_loader.load_sprite(RgbaImage::new(32, 16)))
+        }
+    ).unwrap();
+    (font, sprite_loader)
+}
+
+fn assert_describes_test_font(font: &BitmapFont) {
+    assert_eq!(font.line_height(), 10f32);
+    assert_eq!(font.base(), 8f32);
+    assert_eq!(font.pages().len(), 1);
+
+    let v = font.glyph('V').unwrap();
+    assert_eq!(v.source, Rectangle::new(8f32, 6f32, 5f32, 6f32));
+    assert_eq!(v.offset, Vector2::new(1f32, 0f32));
+    assert_eq!(v.advance, 6f32);
+    assert_eq!(font.kerning('A', 'V'), -2f32);
+    assert_eq!(font.kerning('V', 'A'), 0f32);
+    assert!(font.glyph('B').is_none());
+}
+
+#[test]
+fn text_descriptors_are_parsed() {
+    let (font, _) = load(TEXT_DESCRIPTOR.as_bytes());
+    assert_describes_test_font(&font);
+}
+
+#[test]
+fn binary_descriptors_are_parsed() {
+    let (font, _) = load(&binary_descriptor());
+    assert_describes_test_font(&font);
+}
+
+#[test]
+fn text_is_placed_with_kerning_and_line_height() {
+    let (font, _) = load(TEXT_DESCRIPTOR.as_bytes());
+    let draw_data = font.draw_data("AV A\nV", Vector2::new(10f32, 100f32), Color::new(1f32, 0f32, 0f32, 1f32), 2f32);
+
+    let positions: Vec<_> = draw_data.iter().map(|draw_data| draw_data.position).collect();
+    assert_eq!(
+        positions,
+        vec![
+            Vector2::new(10f32, 84f32),
+            Vector2::new(10f32 + (7f32 - 2f32 + 1f32) * 2f32, 84f32),
+            Vector2::new(10f32 + (7f32 - 2f32 + 6f32 + 3f32) * 2f32, 84f32),
+            Vector2::new(12f32, 64f32)
+        ]
+    );
+    assert!(draw_data.iter().all(|draw_data| draw_data.scale == Vector2::ONE * 2f32 && draw_data.color.red == 1f32));
+}
+
+#[test]
+fn fonts_load_their_pages_next_to_the_descriptor() {
+    let directory = std::env::temp_dir().join(format!("sprite-batching-font-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("test.fnt"), TEXT_DESCRIPTOR).unwrap();
+    RgbaImage::new(32, 16).save(directory.join("test font.png")).unwrap();
+
+    let mut sprite_loader = SpriteLoader::new();
+    let font = BitmapFont::load_file(directory.join("test.fnt"), &mut sprite_loader);
+    let missing = BitmapFont::load_file(directory.join("missing.fnt"), &mut sprite_loader);
+    fs::remove_dir_all(&directory).unwrap();
+
+    assert_describes_test_font(&font.unwrap());
+    assert!(matches!(missing, Err(FontError::Io(_))));
+}
+
+#[test]
+fn malformed_descriptors_are_rejected() {
+    let result = BitmapFont::from_bytes(b"char id=65 x=zero", |_| unreachable!());
+    assert!(matches!(result, Err(FontError::Parse(_))));
+    let result = BitmapFont::from_bytes(b"BMF\x03\x02\x08\x00\x00\x00\x0a", |_| unreachable!());
+    assert!(matches!(result, Err(FontError::Parse(_))));
+
+    // Page ids are bounded by the page count of `common`, or by a sane maximum without one.
+    let result = BitmapFont::from_bytes(b"page id=2000000000 file=\"a.png\"", |_| unreachable!());
+    assert!(matches!(result, Err(FontError::Parse(_))));
+    let result = BitmapFont::from_bytes(b"common lineHeight=8 base=6 pages=1\npage id=1 file=\"a.png\"", |_| unreachable!());
+    assert!(matches!(result, Err(FontError::Parse(_))));
+}
+
+fn cantarell(size: f32, atlas_dimensions: (u32, u32), sprite_loader: &mut SpriteLoader) -> TrueTypeFont {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "fonts", "Cantarell-Regular.ttf"].iter().collect();
+    TrueTypeFont::load_file(path, size, atlas_dimensions, sprite_loader).unwrap()
+}
+
+#[test]
+fn outline_glyphs_are_rasterized_once_per_size() {
+    let mut sprite_loader = SpriteLoader::new();
+    let font = cantarell(16f32, (128, 128), &mut sprite_loader);
+
+    let small = font.draw_data("aa b", Vector2::ZERO, Color::new(1f32, 1f32, 1f32, 1f32), 1f32);
+    let writes = font.take_writes();
+    assert_eq!(small.len(), 3);
+    assert_eq!(writes.len(), 2);
+    assert!(writes.iter().all(|write| write.render_target == font.render_target()));
+    assert_eq!(small[0].source, small[1].source);
+    assert!(small[1].position.x > small[0].position.x);
+
+    let again = font.draw_data("ba", Vector2::ZERO, Color::new(1f32, 1f32, 1f32, 1f32), 1f32);
+    assert_eq!(again.len(), 2);
+    assert!(font.take_writes().is_empty());
+
+    let large = font.draw_data("a", Vector2::ZERO, Color::new(1f32, 1f32, 1f32, 1f32), 2f32);
+    assert_eq!(font.take_writes().len(), 1);
+    assert!(large[0].source.unwrap().height > small[0].source.unwrap().height * 1.5);
+    assert_eq!(font.line_metrics(2f32).line_height, font.line_metrics(1f32).line_height * 2f32);
+}
+
+#[test]
+fn full_atlases_evict_the_least_recently_used_glyphs() {
+    let mut sprite_loader = SpriteLoader::new();
+    let font = cantarell(24f32, (40, 40), &mut sprite_loader);
+
+    let text = "abcdefgh";
+    for (segment, character) in text.chars().enumerate() {
+        font.begin_segment(segment as u64 + 1);
+        font.draw_data(&character.to_string(), Vector2::ZERO, Color::new(1f32, 1f32, 1f32, 1f32), 1f32);
+        font.take_writes();
+    }
+    assert!(font.cached_glyphs() < text.len());
+
+    // The glyph of the previous segment is still cached, while the first ones were evicted to make room.
+    font.begin_segment(100);
+    font.draw_data("h", Vector2::ZERO, Color::new(1f32, 1f32, 1f32, 1f32), 1f32);
+    assert!(font.take_writes().is_empty());
+    font.begin_segment(101);
+    font.draw_data("a", Vector2::ZERO, Color::new(1f32, 1f32, 1f32, 1f32), 1f32);
+    assert_eq!(font.take_writes().len(), 1);
+
+    // Glyphs of the current segment are never evicted, so those that don't fit are skipped.
+    font.begin_segment(102);
+    let draw_data = font.draw_data(text, Vector2::ZERO, Color::new(1f32, 1f32, 1f32, 1f32), 1f32);
+    let writes = font.take_writes();
+    let mut sources: Vec<_> = draw_data.iter().map(|draw_data| draw_data.source.unwrap().position.as_array()).collect();
+    sources.dedup();
+    assert!(draw_data.len() < text.len());
+    assert_eq!(sources.len(), draw_data.len());
+    assert!(writes.len() < text.len());
+    assert!(matches!(font.take_error(), Some(FontError::AtlasFull(_))));
+    assert!(font.take_error().is_none());
+}
+
+#[test]
+fn glyphs_are_kept_until_the_segment_drawing_them_ends() {
+    let mut sprite_loader = SpriteLoader::new();
+    let font = cantarell(24f32, (40, 40), &mut sprite_loader);
+    let white = Color::new(1f32, 1f32, 1f32, 1f32);
+
+    // The second text of the segment cannot evict the glyphs of the first one, which are drawn at the same time.
+    font.begin_segment(1);
+    let first = font.draw_data("ab", Vector2::ZERO, white, 1f32);
+    font.take_writes();
+    let second = font.draw_data("cdefgh", Vector2::ZERO, white, 1f32);
+    font.take_writes();
+    assert_eq!(first.len(), 2);
+    assert!(second.len() < 6);
+    for draw_data in &second {
+        let source = draw_data.source.unwrap();
+        assert!(first.iter().all(|first| !first.source.unwrap().intersects(&source)));
+    }
+
+    // Once a new segment begins, the glyphs of the previous one make room again.
+    font.take_error();
+    font.begin_segment(2);
+    assert_eq!(font.draw_data("gh", Vector2::ZERO, white, 1f32).len(), 2);
+    assert!(font.take_error().is_none());
+}
+
+struct Overflow {
+    font: Option<TrueTypeFont>
+}
+
+impl ApplicationContext for Overflow {
+    fn new() -> Self {
+        Self { font: None }
+    }
+
+    fn load(&mut self, sprite_loader: &mut SpriteLoader) {
+        self.font = Some(cantarell(24f32, (40, 40), sprite_loader));
+    }
+
+    fn draw(&self, sprite_batch: &mut SpriteBatch, renderer: &mut dyn Renderer) -> Result<(), SpriteBatchError> {
+        sprite_batch.begin(SpriteSortMode::Deferred);
+        sprite_batch.draw_text(self.font.as_ref().unwrap(), "abcdefgh", Vector2::ZERO, Color::new(1f32, 1f32, 1f32, 1f32), 1f32);
+        sprite_batch.end(renderer)
+    }
+}
+
+#[test]
+fn segments_with_more_glyphs_than_the_atlas_holds_report_it() {
+    let mut application = HeadlessApplication::<Overflow>::new(8, 8);
+    assert!(matches!(application.draw(), Err(SpriteBatchError::Font(FontError::AtlasFull(_)))));
+}
+
+#[test]
+fn segments_whose_glyphs_cannot_be_written_draw_nothing_and_keep_font_errors() {
+    let mut sprite_loader = SpriteLoader::new();
+    let font = cantarell(24f32, (40, 40), &mut sprite_loader);
+    let white = Color::new(1f32, 1f32, 1f32, 1f32);
+
+    // The renderer never created the atlas of the font, so writing its glyphs fails.
+    let mut renderer = SoftwareRenderer::new(vec![RgbaImage::from_pixel(1, 1, Rgba([255, 255, 255, 255]))], 8, 8);
+    let mut sprite_batch = SpriteBatch::new((8, 8));
+    sprite_batch.begin(SpriteSortMode::Deferred);
+    sprite_batch.draw(DrawData { position: Vector2::new(-4f32, -4f32), scale: Vector2::ONE * 8f32, ..Default::default() });
+    sprite_batch.draw_text(&font, "abcdefgh", Vector2::ZERO, white, 1f32);
+    assert!(matches!(sprite_batch.end(&mut renderer), Err(SpriteBatchError::UnknownRenderTarget)));
+
+    // Neither the sprites of the failed segment nor its writes are submitted with the next one, which reports the overflow.
+    renderer.update_textures(&sprite_loader);
+    sprite_batch.begin(SpriteSortMode::Deferred);
+    assert!(matches!(sprite_batch.end(&mut renderer), Err(SpriteBatchError::Font(FontError::AtlasFull(_)))));
+    assert!(renderer.image().pixels().all(|pixel| pixel.0 == [0, 0, 0, 0]));
+
+    sprite_batch.begin(SpriteSortMode::Deferred);
+    sprite_batch.end(&mut renderer).unwrap();
+}
+
+struct Label {
+    font: Option<TrueTypeFont>
+}
+
+impl ApplicationContext for Label {
+    fn new() -> Self {
+        Self { font: None }
+    }
+
+    fn load(&mut self, sprite_loader: &mut SpriteLoader) {
+        self.font = Some(cantarell(24f32, (64, 64), sprite_loader));
+    }
+
+    fn draw(&self, sprite_batch: &mut SpriteBatch, renderer: &mut dyn Renderer) -> Result<(), SpriteBatchError> {
+        sprite_batch.draw_parameters.blend = Blend::alpha_blending();
+        sprite_batch.begin(SpriteSortMode::Deferred);
+        sprite_batch.clear_color(Color::new(0f32, 0f32, 0f32, 1f32));
+        sprite_batch.draw_text(self.font.as_ref().unwrap(), "Hi", Vector2::new(-16f32, 12f32), Color::new(1f32, 0f32, 0f32, 1f32), 1f32);
+        sprite_batch.end(renderer)
+    }
+}
+
+#[test]
+fn outline_glyphs_are_written_into_the_atlas_before_they_are_drawn() {
+    let mut application = HeadlessApplication::<Label>::new(32, 24);
+    let image = application.draw().unwrap();
+
+    let red = image.pixels().filter(|pixel| pixel.0[0] > 200 && pixel.0[1] == 0).count();
+    assert!(red > 20, "{red} red pixels");
+    assert!(image.pixels().all(|pixel| pixel.0[1] == 0 && pixel.0[2] == 0));
+}
@@ -0,0 +1,237 @@
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+use sprite_batching::{
+    Color,
+    DrawData,
+    Mesh,
+    Sprite,
+    SpriteLoader,
+    Vertex,
+    image::RgbaImage,
+    math::{Matrix4x4, Rectangle, Vector2}
+};
+
+fn identity() -> Matrix4x4 {
+    Matrix4x4::new_scaling(1f32, 1f32, 1f32)
+}
+
+fn load_sprites() -> (Sprite, Sprite) {
+    let mut sprite_loader = SpriteLoader::new();
+    let pixel = sprite_loader.load_sprite(RgbaImage::new(1, 1));
+    let slime = sprite_loader.load_sprite(RgbaImage::new(16, 48));
+    (pixel, slime)
+}
+
+fn assert_positions_near(vertices: &[Vertex], expected: [[f32; 3]; 4]) {
+    for (vertex, expected) in vertices.iter().zip(expected) {
+        for (actual, expected) in vertex.position.iter().zip(expected) {
+            assert!((actual - expected).abs() < 1e-4, "{:?} != {:?}", vertex.position, expected);
+        }
+    }
+}
+
+#[test]
+fn untransformed_sprite_covers_its_dimensions() {
+    let (_, slime) = load_sprites();
+    let mesh = Mesh::from_draw_data(
+        &[DrawData { sprite: slime, position: Vector2::new(10f32, 20f32), depth: 0.5, ..Default::default() }],
+        identity(),
+        (16, 48)
+    );
+
+    let white = [1f32, 1f32, 1f32, 1f32];
+    assert_eq!(
+        mesh.vertices,
+        vec![
+            Vertex { index: 1, position: [10f32, 20f32, 0.5], uv: [0f32, 0f32], color: white },
+            Vertex { index: 1, position: [26f32, 20f32, 0.5], uv: [1f32, 0f32], color: white },
+            Vertex { index: 1, position: [26f32, 68f32, 0.5], uv: [1f32, 1f32], color: white },
+            Vertex { index: 1, position: [10f32, 68f32, 0.5], uv: [0f32, 1f32], color: white }
+        ]
+    );
+    assert_eq!(mesh.indices, vec![0, 1, 2, 2, 3, 0]);
+}
+
+#[test]
+fn source_rectangle_is_normalized_by_the_texture_dimensions() {
+    let (_, slime) = load_sprites();
+    let mesh = Mesh::from_draw_data(
+        &[DrawData { sprite: slime, source: Some(Rectangle::new(16f32, 8f32, 16f32, 8f32)), ..Default::default() }],
+        identity(),
+        (64, 32)
+    );
+
+    let uvs: Vec<[f32; 2]> = mesh.vertices.iter().map(|vertex| vertex.uv).collect();
+    assert_eq!(uvs, vec![[0.25, 0.25], [0.5, 0.25], [0.5, 0.5], [0.25, 0.5]]);
+    assert_eq!(mesh.vertices[2].position, [16f32, 8f32, 0f32]);
+}
+
+#[test]
+fn origin_scale_and_projection_are_applied_in_order() {
+    let (_, slime) = load_sprites();
+    let mesh = Mesh::from_draw_data(
+        &[
+            DrawData {
+                sprite: slime,
+                source: Some(Rectangle::new(0f32, 0f32, 16f32, 16f32)),
+                position: Vector2::new(32f32, -32f32),
+                origin: Vector2::new(8f32, 8f32),
+                scale: Vector2::new(2f32, 4f32),
+                ..Default::default()
+            }
+        ],
+        Matrix4x4::new_scaling(1f32 / 64f32, 1f32 / 128f32, 1f32),
+        (16, 48)
+    );
+
+    let positions: Vec<[f32; 3]> = mesh.vertices.iter().map(|vertex| vertex.position).collect();
+    assert_eq!(
+        positions,
+        vec![
+            [0.25, -0.5, 0f32],
+            [0.75, -0.5, 0f32],
+            [0.75, 0f32, 0f32],
+            [0.25, 0f32, 0f32]
+        ]
+    );
+}
+
+#[test]
+fn rotation_turns_counterclockwise_around_the_origin() {
+    let (_, slime) = load_sprites();
+    let mesh = Mesh::from_draw_data(
+        &[
+            DrawData {
+                sprite: slime,
+                source: Some(Rectangle::new(0f32, 0f32, 16f32, 16f32)),
+                origin: Vector2::new(8f32, 8f32),
+                rotation: FRAC_PI_2,
+                ..Default::default()
+            }
+        ],
+        identity(),
+        (16, 48)
+    );
+
+    assert_positions_near(
+        &mesh.vertices,
+        [
+            [8f32, -8f32, 0f32],
+            [8f32, 8f32, 0f32],
+            [-8f32, 8f32, 0f32],
+            [-8f32, -8f32, 0f32]
+        ]
+    );
+}
+
+#[test]
+fn sprites_keep_their_colour_channels_and_indices() {
+    let (pixel, slime) = load_sprites();
+    let mesh = Mesh::from_draw_data(
+        &[
+            DrawData { sprite: slime, color: Color::new(0.1, 0.2, 0.3, 0.4), ..Default::default() },
+            DrawData { sprite: pixel, color: Color::new(1f32, 0.5, 0.25, 1f32), ..Default::default() }
+        ],
+        identity(),
+        (16, 48)
+    );
+
+    assert_eq!(mesh.vertices.len(), 2 * Mesh::VERTICES_PER_SPRITE);
+    assert!(mesh.vertices[..4].iter().all(|vertex| vertex.color == [0.1, 0.2, 0.3, 0.4] && vertex.index == 1));
+    assert!(mesh.vertices[4..].iter().all(|vertex| vertex.color == [1f32, 0.5, 0.25, 1f32] && vertex.index == 0));
+    assert_eq!(mesh.vertices[6].uv, [1f32 / 16f32, 1f32 / 48f32]);
+    assert_eq!(mesh.indices, vec![0, 1, 2, 2, 3, 0, 4, 5, 6, 6, 7, 4]);
+}
+
+#[test]
+fn corner_colours_follow_the_vertices_and_are_tinted() {
+    let (pixel, _) = load_sprites();
+    let mesh = Mesh::from_draw_data(
+        &[
+            DrawData {
+                sprite: pixel,
+                color: Color::new(0.5, 1f32, 1f32, 1f32),
+                corner_colors: Some([
+                    Color::new(1f32, 0f32, 0f32, 1f32),
+                    Color::new(0f32, 1f32, 0f32, 1f32),
+                    Color::new(0f32, 0f32, 1f32, 1f32),
+                    Color::new(1f32, 1f32, 1f32, 0f32)
+                ]),
+                flip_x: true,
+                ..Default::default()
+            }
+        ],
+        identity(),
+        (1, 1)
+    );
+
+    let colors: Vec<_> = mesh.vertices.iter().map(|vertex| vertex.color).collect();
+    assert_eq!(
+        colors,
+        vec![[0.5, 0f32, 0f32, 1f32], [0f32, 1f32, 0f32, 1f32], [0f32, 0f32, 1f32, 1f32], [0.5, 1f32, 1f32, 0f32]]
+    );
+    assert_eq!(mesh.vertices[0].position, [0f32, 0f32, 0f32]);
+}
+
+#[test]
+fn atlas_sprites_offset_their_texture_coordinates() {
+    let mut sprite_loader = SpriteLoader::new_atlas(8, 8);
+    let pixel = sprite_loader.load_sprite(RgbaImage::new(1, 1));
+    let wide = sprite_loader.load_sprite(RgbaImage::new(4, 2));
+    let second_pixel = sprite_loader.load_sprite(RgbaImage::new(1, 1));
+
+    assert_eq!((pixel.index(), pixel.position()), (0, (0, 0)));
+    assert_eq!((wide.index(), wide.position()), (0, (0, 2)));
+    assert_eq!((second_pixel.index(), second_pixel.position()), (0, (2, 0)));
+    assert_eq!(sprite_loader.create_layers().len(), 1);
+
+    let mesh = Mesh::from_draw_data(
+        &[DrawData { sprite: wide, source: Some(Rectangle::new(2f32, 0f32, 2f32, 2f32)), ..Default::default() }],
+        identity(),
+        (8, 8)
+    );
+    assert_eq!(mesh.vertices[0].uv, [0.25, 0.25]);
+    assert_eq!(mesh.vertices[2].uv, [0.5, 0.5]);
+}
+
+#[test]
+fn flipping_swaps_texture_coordinates_but_keeps_positions() {
+    let (_, slime) = load_sprites();
+    let draw_data = |flip_x, flip_y| DrawData { sprite: slime, origin: Vector2::new(4f32, 0f32), flip_x, flip_y, ..Default::default() };
+    let unflipped = Mesh::from_draw_data(&[draw_data(false, false)], identity(), (16, 48));
+    let flipped = Mesh::from_draw_data(&[draw_data(true, true)], identity(), (16, 48));
+
+    let positions = |mesh: &Mesh| mesh.vertices.iter().map(|vertex| vertex.position).collect::<Vec<_>>();
+    assert_eq!(positions(&flipped), positions(&unflipped));
+    let uvs: Vec<[f32; 2]> = flipped.vertices.iter().map(|vertex| vertex.uv).collect();
+    assert_eq!(uvs, vec![[1f32, 1f32], [0f32, 1f32], [0f32, 0f32], [1f32, 0f32]]);
+}
+
+#[test]
+fn transform_is_applied_between_scale_and_rotation() {
+    let (_, slime) = load_sprites();
+    let mesh = Mesh::from_draw_data(
+        &[
+            DrawData {
+                sprite: slime,
+                source: Some(Rectangle::new(0f32, 0f32, 16f32, 16f32)),
+                scale: Vector2::new(1f32, 0.5),
+                transform: Some(Matrix4x4::new_skew(FRAC_PI_4, 0f32)),
+                rotation: FRAC_PI_2,
+                ..Default::default()
+            }
+        ],
+        identity(),
+        (16, 48)
+    );
+
+    // Scaled to 16x8, skewed so the top edge moves 8 to the right, then turned a quarter counterclockwise.
+    assert_positions_near(
+        &mesh.vertices,
+        [
+            [0f32, 0f32, 0f32],
+            [0f32, 16f32, 0f32],
+            [-8f32, 24f32, 0f32],
+            [-8f32, 8f32, 0f32]
+        ]
+    );
+}
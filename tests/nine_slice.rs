@@ -0,0 +1,79 @@
+use sprite_batching::{
+    Insets,
+    Mesh,
+    NineSlice,
+    SliceFill,
+    SpriteLoader,
+    image::RgbaImage,
+    math::{Matrix4x4, Rectangle}
+};
+
+/// The bottom-left and top-right corners of every quad, and the bottom-left and top-right of its texture coordinates.
+fn quads(nine_slice: &NineSlice, texture_dimensions: (u32, u32)) -> Vec<([f32; 4], [f32; 4])> {
+    let mesh = Mesh::from_draw_data(&nine_slice.draw_data(), Matrix4x4::new_scaling(1f32, 1f32, 1f32), texture_dimensions);
+    mesh.vertices.chunks_exact(Mesh::VERTICES_PER_SPRITE).map(
+        |quad| (
+            [quad[0].position[0], quad[0].position[1], quad[2].position[0], quad[2].position[1]],
+            [quad[0].uv[0], quad[0].uv[1], quad[2].uv[0], quad[2].uv[1]]
+        )
+    ).collect()
+}
+
+#[test]
+fn corners_keep_their_size_while_edges_and_centre_stretch() {
+    let mut sprite_loader = SpriteLoader::new();
+    let panel = sprite_loader.load_sprite(RgbaImage::new(4, 4));
+    let nine_slice = NineSlice {
+        sprite: panel,
+        insets: Insets::new(1f32, 1f32, 2f32, 1f32),
+        destination: Rectangle::new(10f32, 20f32, 13f32, 6f32),
+        ..Default::default()
+    };
+
+    let quads = quads(&nine_slice, (4, 4));
+    assert_eq!(quads.len(), 9);
+    assert_eq!(quads[0], ([10f32, 20f32, 11f32, 21f32], [0f32, 0f32, 0.25, 0.25]));
+    assert_eq!(quads[1], ([11f32, 20f32, 21f32, 21f32], [0.25, 0f32, 0.5, 0.25]));
+    assert_eq!(quads[2], ([21f32, 20f32, 23f32, 21f32], [0.5, 0f32, 1f32, 0.25]));
+    assert_eq!(quads[4], ([11f32, 21f32, 21f32, 25f32], [0.25, 0.25, 0.5, 0.75]));
+    assert_eq!(quads[8], ([21f32, 25f32, 23f32, 26f32], [0.5, 0.75, 1f32, 1f32]));
+}
+
+#[test]
+fn tiled_edges_cut_off_their_last_repetition() {
+    let mut sprite_loader = SpriteLoader::new();
+    let panel = sprite_loader.load_sprite(RgbaImage::new(4, 4));
+    let nine_slice = NineSlice {
+        sprite: panel,
+        insets: Insets::uniform(1f32),
+        destination: Rectangle::new(0f32, 0f32, 7f32, 4f32),
+        fill: SliceFill::Tile,
+        ..Default::default()
+    };
+
+    let quads = quads(&nine_slice, (4, 4));
+    let bottom_row: Vec<_> = quads.iter().filter(|(position, _)| position[1] == 0f32).collect();
+    assert_eq!(quads.len(), 5 * 3);
+    assert_eq!(bottom_row.len(), 5);
+    assert_eq!(*bottom_row[1], ([1f32, 0f32, 3f32, 1f32], [0.25, 0f32, 0.75, 0.25]));
+    assert_eq!(*bottom_row[3], ([5f32, 0f32, 6f32, 1f32], [0.25, 0f32, 0.5, 0.25]));
+    assert_eq!(*bottom_row[4], ([6f32, 0f32, 7f32, 1f32], [0.75, 0f32, 1f32, 0.25]));
+}
+
+#[test]
+fn corners_shrink_to_fit_small_destinations() {
+    let mut sprite_loader = SpriteLoader::new();
+    let panel = sprite_loader.load_sprite(RgbaImage::new(4, 4));
+    let nine_slice = NineSlice {
+        sprite: panel,
+        insets: Insets::uniform(2f32),
+        destination: Rectangle::new(0f32, 0f32, 2f32, 8f32),
+        ..Default::default()
+    };
+
+    let quads = quads(&nine_slice, (4, 4));
+    assert_eq!(quads.len(), 4);
+    assert_eq!(quads[0].0, [0f32, 0f32, 1f32, 2f32]);
+    assert_eq!(quads[1].0, [1f32, 0f32, 2f32, 2f32]);
+    assert_eq!(quads[3].0, [1f32, 6f32, 2f32, 8f32]);
+}
@@ -0,0 +1,154 @@
+use std::cell::Cell;
+use sprite_batching::{
+    ApplicationContext,
+    Color,
+    DrawCall,
+    DrawData,
+    HeadlessApplication,
+    Mesh,
+    RenderTarget,
+    Renderer,
+    Shape,
+    SpriteBatch,
+    SpriteBatchError,
+    SpriteSortMode,
+    image::{Rgba, RgbaImage},
+    math::{Rectangle, Vector2}
+};
+
+/// The signed area of every triangle of `shape`, positive for counterclockwise ones.
+fn triangle_areas(shape: &Shape) -> Vec<f32> {
+    shape.indices.chunks_exact(3).map(
+        |triangle| {
+            let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|index| shape.points[index as usize]);
+            ((b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)) / 2f32
+        }
+    ).collect()
+}
+
+#[test]
+fn concave_polygons_are_covered_by_counterclockwise_triangles() {
+    // An L with its inner corner at (1, 1), listed clockwise.
+    let points = [
+        Vector2::new(0f32, 0f32),
+        Vector2::new(0f32, 3f32),
+        Vector2::new(1f32, 3f32),
+        Vector2::new(1f32, 1f32),
+        Vector2::new(3f32, 1f32),
+        Vector2::new(3f32, 0f32)
+    ];
+    let shape = Shape::polygon(&points);
+
+    let areas = triangle_areas(&shape);
+    assert_eq!(areas.len(), points.len() - 2);
+    assert!(areas.iter().all(|area| *area > 0f32), "{areas:?}");
+    assert!((areas.iter().sum::<f32>() - 5f32).abs() < 1e-5);
+}
+
+#[test]
+fn lines_are_centred_on_their_segment() {
+    let shape = Shape::line(Vector2::new(1f32, 1f32), Vector2::new(5f32, 1f32), 2f32);
+
+    assert_eq!(
+        shape.points,
+        vec![Vector2::new(1f32, 0f32), Vector2::new(5f32, 0f32), Vector2::new(1f32, 2f32), Vector2::new(5f32, 2f32)]
+    );
+    assert_eq!(triangle_areas(&shape).iter().sum::<f32>(), 8f32);
+}
+
+#[test]
+fn outlines_of_rectangles_stay_inside_them() {
+    let rectangle = Rectangle::new(0f32, 0f32, 4f32, 3f32);
+    let shape = Shape::rectangle_outline(rectangle, 1f32);
+
+    assert!(shape.points.iter().all(|point| (0f32..=4f32).contains(&point.x) && (0f32..=3f32).contains(&point.y)));
+    assert!((triangle_areas(&shape).iter().sum::<f32>() - (12f32 - 2f32)).abs() < 1e-5);
+}
+
+#[test]
+fn closed_strokes_miter_their_corners() {
+    let square = [Vector2::new(0f32, 0f32), Vector2::new(2f32, 0f32), Vector2::new(2f32, 2f32), Vector2::new(0f32, 2f32)];
+    let shape = Shape::stroke(&square, 1f32, true);
+
+    assert!(shape.points[0].distance(&Vector2::new(-0.5, -0.5)) < 1e-5, "{:?}", shape.points[0]);
+    assert!(shape.points[4].distance(&Vector2::new(0.5, 0.5)) < 1e-5, "{:?}", shape.points[4]);
+    assert!((triangle_areas(&shape).iter().sum::<f32>() - (9f32 - 1f32)).abs() < 1e-5);
+}
+
+#[test]
+fn arcs_end_on_their_end_angle() {
+    let shape = Shape::arc(Vector2::ZERO, 2f32, 0f32, std::f32::consts::PI, 4, 0.5);
+    let last = shape.points.len() / 2 - 1;
+
+    assert_eq!(shape.points.len(), 10);
+    assert!((shape.points[last].distance(&Vector2::new(-2f32, 0f32)) - 0.25).abs() < 1e-5);
+}
+
+/// Counts the draw calls submitted to the renderer it wraps.
+struct CountingRenderer<'a> {
+    renderer: &'a mut dyn Renderer,
+    draws: usize
+}
+
+impl Renderer for CountingRenderer<'_> {
+    fn dimensions(&self) -> (u32, u32) {
+        self.renderer.dimensions()
+    }
+
+    fn texture_dimensions(&self) -> (u32, u32) {
+        self.renderer.texture_dimensions()
+    }
+
+    fn draw(&mut self, mesh: &Mesh, draw_call: &DrawCall) -> Result<(), SpriteBatchError> {
+        self.draws += 1;
+        self.renderer.draw(mesh, draw_call)
+    }
+
+    fn clear_render_target(&mut self, target: RenderTarget, color: Color) -> Result<(), SpriteBatchError> {
+        self.renderer.clear_render_target(target, color)
+    }
+
+    fn write_render_target(&mut self, target: RenderTarget, position: (u32, u32), image: &RgbaImage) -> Result<(), SpriteBatchError> {
+        self.renderer.write_render_target(target, position, image)
+    }
+
+    fn clear_mask(&mut self, target: Option<RenderTarget>) -> Result<(), SpriteBatchError> {
+        self.renderer.clear_mask(target)
+    }
+}
+
+struct Shapes {
+    draws: Cell<usize>
+}
+
+impl ApplicationContext for Shapes {
+    fn new() -> Self {
+        Self { draws: Cell::new(0) }
+    }
+
+    fn draw(&self, sprite_batch: &mut SpriteBatch, renderer: &mut dyn Renderer) -> Result<(), SpriteBatchError> {
+        let mut renderer = CountingRenderer { renderer, draws: 0 };
+        sprite_batch.begin(SpriteSortMode::Deferred);
+        sprite_batch.clear_color(Color::new(0f32, 0f32, 0f32, 1f32));
+        sprite_batch.draw_rectangle(Rectangle::new(-8f32, -8f32, 4f32, 4f32), Color::new(1f32, 0f32, 0f32, 1f32));
+        sprite_batch.draw_circle(Vector2::new(4f32, 4f32), 3f32, 16, Color::new(0f32, 1f32, 0f32, 1f32));
+        sprite_batch.draw_line(Vector2::new(-8f32, 6f32), Vector2::new(0f32, 6f32), 2f32, Color::new(0f32, 0f32, 1f32, 1f32));
+        sprite_batch.draw(DrawData { position: Vector2::new(6f32, -8f32), scale: Vector2::ONE * 2f32, ..Default::default() });
+        sprite_batch.end(&mut renderer)?;
+        self.draws.set(renderer.draws);
+        Ok(())
+    }
+}
+
+#[test]
+fn shapes_share_a_draw_call_with_sprites() {
+    let mut application = HeadlessApplication::<Shapes>::new(16, 16);
+    let image = application.draw().unwrap().clone();
+
+    assert_eq!(application.context().draws.get(), 1);
+    assert_eq!(*image.get_pixel(1, 14), Rgba([255, 0, 0, 255]));
+    assert_eq!(*image.get_pixel(12, 4), Rgba([0, 255, 0, 255]));
+    assert_eq!(*image.get_pixel(4, 1), Rgba([0, 0, 255, 255]));
+    assert_eq!(*image.get_pixel(15, 15), Rgba([255, 255, 255, 255]));
+    assert_eq!(*image.get_pixel(8, 8), Rgba([0, 0, 0, 255]));
+}
@@ -0,0 +1,583 @@
+use std::{
+    env,
+    f32::consts::FRAC_PI_4,
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime}
+};
+use sprite_batching::{
+    ApplicationContext,
+    BlendMode,
+    Color,
+    DrawData,
+    Effect,
+    Fragment,
+    HeadlessApplication,
+    MaskMode,
+    RenderTarget,
+    Renderer,
+    Shader,
+    ShaderSource,
+    Sprite,
+    SpriteBatch,
+    SpriteBatchError,
+    SpriteError,
+    SpriteLoader,
+    SpriteSortMode,
+    UniformValue,
+    Uniforms,
+    DEFAULT_FRAGMENT_SHADER,
+    glium::{Blend, uniforms::MagnifySamplerFilter},
+    image::{self, ImageOutputFormat, Rgba, RgbaImage},
+    math::{Rectangle, Vector2}
+};
+
+/// Compares `image` against `tests/reference/<name>.png`, allowing a rounding difference of one per channel.
+/// Set `UPDATE_REFERENCE_IMAGES` to write the reference instead.
+fn assert_matches_reference(image: &RgbaImage, name: &str) {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "reference", &format!("{name}.png")].iter().collect();
+    if env::var_os("UPDATE_REFERENCE_IMAGES").is_some() {
+        image.save(&path).unwrap();
+        return;
+    }
+
+    let reference = image::open(&path).unwrap().into_rgba8();
+    assert_eq!(image.dimensions(), reference.dimensions());
+    for ((x, y, actual), expected) in image.enumerate_pixels().zip(reference.pixels()) {
+        let matches = actual.0.iter().zip(expected.0).all(|(actual, expected)| actual.abs_diff(expected) <= 1);
+        assert!(matches, "pixel ({x}, {y}) of {name} is {actual:?}, expected {expected:?}");
+    }
+}
+
+/// A 2x2 sprite with a distinct colour in every corner, to catch flipped texture coordinates.
+fn corners() -> RgbaImage {
+    RgbaImage::from_fn(2, 2, |x, y| match (x, y) {
+        (0, 0) => Rgba([255, 0, 0, 255]),
+        (1, 0) => Rgba([0, 255, 0, 255]),
+        (0, 1) => Rgba([0, 0, 255, 255]),
+        _ => Rgba([255, 255, 255, 255])
+    })
+}
+
+struct Scene {
+    corners: Sprite,
+    strip: Sprite
+}
+
+impl ApplicationContext for Scene {
+    fn new() -> Self {
+        Self { corners: Sprite::default(), strip: Sprite::default() }
+    }
+
+    fn load(&mut self, sprite_loader: &mut SpriteLoader) {
+        self.corners = sprite_loader.load_sprite(corners());
+        self.strip = sprite_loader.load_sprite(
+            RgbaImage::from_fn(4, 1, |x, _| Rgba([0, (x * 85) as u8, 255, 255]))
+        );
+    }
+
+    fn draw(&self, sprite_batch: &mut SpriteBatch, renderer: &mut dyn Renderer) -> Result<(), SpriteBatchError> {
+        sprite_batch.sampler_behaviour.magnify_filter = MagnifySamplerFilter::Nearest;
+        sprite_batch.draw_parameters.blend = Blend::alpha_blending();
+
+        sprite_batch.begin(SpriteSortMode::Deferred);
+        sprite_batch.clear_color(Color::new(0.25, 0.25, 0.25, 1f32));
+        sprite_batch.draw(
+            DrawData { sprite: self.corners, position: Vector2::new(-28f32, 14f32), scale: Vector2::ONE * 8f32, ..Default::default() }
+        );
+        sprite_batch.draw(
+            DrawData {
+                sprite: self.strip,
+                position: Vector2::new(12f32, 16f32),
+                origin: Vector2::new(2f32, 0.5),
+                scale: Vector2::new(4f32, 8f32),
+                rotation: FRAC_PI_4,
+                ..Default::default()
+            }
+        );
+        sprite_batch.draw(
+            DrawData {
+                position: Vector2::new(-20f32, -28f32),
+                scale: Vector2::new(40f32, 40f32),
+                color: Color::new(1f32, 0f32, 0f32, 0.5),
+                ..Default::default()
+            }
+        );
+        sprite_batch.end(renderer)?;
+
+        sprite_batch.sampler_behaviour.magnify_filter = MagnifySamplerFilter::Linear;
+        sprite_batch.begin(SpriteSortMode::Deferred);
+        sprite_batch.draw(
+            DrawData {
+                sprite: self.corners,
+                source: Some(Rectangle::new(0f32, 0f32, 2f32, 2f32)),
+                position: Vector2::new(8f32, -28f32),
+                scale: Vector2::ONE * 10f32,
+                ..Default::default()
+            }
+        );
+        sprite_batch.end(renderer)
+    }
+}
+
+#[test]
+fn scene_matches_reference() {
+    let mut application = HeadlessApplication::<Scene>::new(64, 64);
+    application.update(0f32);
+    assert_matches_reference(application.draw().unwrap(), "scene");
+}
+
+#[test]
+fn sprite_corners_keep_their_orientation() {
+    let mut application = HeadlessApplication::<Scene>::new(64, 64);
+    let image = application.draw().unwrap();
+
+    // The corners sprite covers (-28, 14) to (-12, 30) in world space, with y pointing up.
+    assert_eq!(*image.get_pixel(6, 5), Rgba([255, 0, 0, 255]));
+    assert_eq!(*image.get_pixel(17, 5), Rgba([0, 255, 0, 255]));
+    assert_eq!(*image.get_pixel(6, 15), Rgba([0, 0, 255, 255]));
+    assert_eq!(*image.get_pixel(17, 15), Rgba([255, 255, 255, 255]));
+}
+
+struct Streaming {
+    frame: u32,
+    sprite: Option<Sprite>
+}
+
+impl ApplicationContext for Streaming {
+    fn new() -> Self {
+        Self { frame: 0, sprite: None }
+    }
+
+    fn update(&mut self, _delta_time: f32, sprite_loader: &mut SpriteLoader) {
+        self.frame += 1;
+        match (self.frame, self.sprite) {
+            (1, _) => self.sprite = Some(sprite_loader.load_sprite(RgbaImage::from_pixel(4, 4, Rgba([255, 0, 0, 255])))),
+            (2, Some(sprite)) => sprite_loader.replace_sprite(sprite, RgbaImage::from_pixel(4, 4, Rgba([0, 0, 255, 255]))).unwrap(),
+            (3, Some(sprite)) => {
+                sprite_loader.unload_sprite(sprite).unwrap();
+                self.sprite = None;
+            },
+            _ => ()
+        }
+    }
+
+    fn draw(&self, sprite_batch: &mut SpriteBatch, renderer: &mut dyn Renderer) -> Result<(), SpriteBatchError> {
+        sprite_batch.begin(SpriteSortMode::Deferred);
+        if let Some(sprite) = self.sprite {
+            sprite_batch.draw(DrawData { sprite, position: Vector2::new(-2f32, -2f32), ..Default::default() });
+        }
+        sprite_batch.end(renderer)
+    }
+}
+
+#[test]
+fn sprites_can_be_loaded_replaced_and_unloaded_while_running() {
+    let mut application = HeadlessApplication::<Streaming>::new(8, 8);
+    let mut center_after_update = || {
+        application.update(0f32);
+        *application.draw().unwrap().get_pixel(4, 4)
+    };
+
+    assert_eq!(center_after_update(), Rgba([255, 0, 0, 255]));
+    assert_eq!(center_after_update(), Rgba([0, 0, 255, 255]));
+    assert_eq!(center_after_update(), Rgba([0, 0, 0, 0]));
+}
+
+#[test]
+fn unloaded_sprites_do_not_match_the_sprites_reusing_their_space() {
+    for mut sprite_loader in [SpriteLoader::new(), SpriteLoader::new_atlas(16, 16)] {
+        let stale = sprite_loader.load_sprite(RgbaImage::new(4, 4));
+        sprite_loader.unload_sprite(stale).unwrap();
+        let sprite = sprite_loader.load_sprite(RgbaImage::new(4, 4));
+        assert_eq!((sprite.index(), sprite.position()), (stale.index(), stale.position()));
+        assert_ne!(sprite, stale);
+
+        assert!(matches!(sprite_loader.replace_sprite(stale, RgbaImage::new(4, 4)), Err(SpriteError::UnknownSprite)));
+        assert!(matches!(sprite_loader.unload_sprite(stale), Err(SpriteError::UnknownSprite)));
+        sprite_loader.unload_sprite(sprite).unwrap();
+    }
+}
+
+/// The file watched by `Reloading`, unique to the test process.
+fn reloaded_path() -> PathBuf {
+    env::temp_dir().join(format!("sprite-batching-reload-{}.png", std::process::id()))
+}
+
+/// Writes `bytes` to the file watched by `Reloading`, setting its modification time so changes do not depend on the clock resolution.
+fn write_reloaded_file(bytes: &[u8], seconds: u64) {
+    let path = reloaded_path();
+    fs::write(&path, bytes).unwrap();
+    let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(seconds);
+    fs::File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+}
+
+fn png(color: [u8; 4]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    RgbaImage::from_pixel(8, 8, Rgba(color)).write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png).unwrap();
+    bytes
+}
+
+struct Reloading {
+    sprite: Sprite,
+    failures: Vec<PathBuf>
+}
+
+impl ApplicationContext for Reloading {
+    fn new() -> Self {
+        Self { sprite: Sprite::default(), failures: Vec::new() }
+    }
+
+    fn load(&mut self, sprite_loader: &mut SpriteLoader) {
+        self.sprite = sprite_loader.load_sprite_file(reloaded_path()).unwrap();
+    }
+
+    fn reload_failed(&mut self, path: &Path, _error: SpriteError) {
+        self.failures.push(path.to_path_buf());
+    }
+
+    fn draw(&self, sprite_batch: &mut SpriteBatch, renderer: &mut dyn Renderer) -> Result<(), SpriteBatchError> {
+        sprite_batch.begin(SpriteSortMode::Deferred);
+        sprite_batch.draw(DrawData { sprite: self.sprite, position: Vector2::new(-4f32, -4f32), ..Default::default() });
+        sprite_batch.end(renderer)
+    }
+}
+
+#[test]
+fn modified_sprite_files_are_reloaded() {
+    write_reloaded_file(&png([255, 0, 0, 255]), 1000);
+    let mut application = HeadlessApplication::<Reloading>::new(8, 8);
+    let mut center_after_update = || {
+        application.update(0f32);
+        (*application.draw().unwrap().get_pixel(4, 4), application.context().failures.len())
+    };
+    assert_eq!(center_after_update(), (Rgba([255, 0, 0, 255]), 0));
+
+    write_reloaded_file(&png([0, 0, 255, 255]), 1001);
+    assert_eq!(center_after_update(), (Rgba([0, 0, 255, 255]), 0));
+
+    // A half-written file is reported and the sprite keeps its pixels until the file changes again.
+    let green = png([0, 255, 0, 255]);
+    write_reloaded_file(&green[..green.len() / 2], 1002);
+    assert_eq!(center_after_update(), (Rgba([0, 0, 255, 255]), 1));
+    assert_eq!(center_after_update(), (Rgba([0, 0, 255, 255]), 1));
+    write_reloaded_file(&green, 1003);
+    assert_eq!(center_after_update(), (Rgba([0, 255, 0, 255]), 1));
+
+    // Files whose modification time did not change are not read again.
+    write_reloaded_file(&png([255, 255, 255, 255]), 1003);
+    assert_eq!(center_after_update(), (Rgba([0, 255, 0, 255]), 1));
+
+    fs::remove_file(reloaded_path()).unwrap();
+    assert_eq!(application.context().failures[0], reloaded_path());
+}
+
+struct Composite {
+    corners: Sprite,
+    render_target: Option<RenderTarget>
+}
+
+impl ApplicationContext for Composite {
+    fn new() -> Self {
+        Self { corners: Sprite::default(), render_target: None }
+    }
+
+    fn load(&mut self, sprite_loader: &mut SpriteLoader) {
+        self.corners = sprite_loader.load_sprite(corners());
+        self.render_target = Some(sprite_loader.create_render_target(4, 4));
+    }
+
+    fn draw(&self, sprite_batch: &mut SpriteBatch, renderer: &mut dyn Renderer) -> Result<(), SpriteBatchError> {
+        let render_target = self.render_target.unwrap();
+        sprite_batch.sampler_behaviour.magnify_filter = MagnifySamplerFilter::Nearest;
+
+        renderer.clear_render_target(render_target, Color::new(0f32, 0f32, 0f32, 0f32))?;
+        sprite_batch.begin_target(SpriteSortMode::Deferred, render_target);
+        sprite_batch.draw(
+            DrawData { sprite: self.corners, position: Vector2::new(-2f32, -2f32), scale: Vector2::ONE * 2f32, ..Default::default() }
+        );
+        sprite_batch.end(renderer)?;
+
+        sprite_batch.begin(SpriteSortMode::Deferred);
+        sprite_batch.draw(
+            DrawData { sprite: render_target.sprite(), position: Vector2::new(-8f32, -8f32), scale: Vector2::ONE * 4f32, ..Default::default() }
+        );
+        sprite_batch.end(renderer)
+    }
+}
+
+#[test]
+fn render_targets_can_be_drawn_as_sprites_in_the_same_frame() {
+    let mut application = HeadlessApplication::<Composite>::new(16, 16);
+    let image = application.draw().unwrap();
+
+    assert_eq!(*image.get_pixel(0, 0), Rgba([255, 0, 0, 255]));
+    assert_eq!(*image.get_pixel(15, 0), Rgba([0, 255, 0, 255]));
+    assert_eq!(*image.get_pixel(0, 15), Rgba([0, 0, 255, 255]));
+    assert_eq!(*image.get_pixel(15, 15), Rgba([255, 255, 255, 255]));
+}
+
+/// Replaces the colour of every texel with the `flash` uniform, keeping its alpha.
+fn flash(fragment: &Fragment, uniforms: &Uniforms) -> [f32; 4] {
+    let Some(UniformValue::Vec3([red, green, blue])) = uniforms.get("flash") else {
+        return fragment.texel;
+    };
+
+    [red, green, blue, fragment.texel[3] * fragment.color[3]]
+}
+
+struct Effects {
+    shader: Shader
+}
+
+impl ApplicationContext for Effects {
+    fn new() -> Self {
+        Self { shader: Shader::default() }
+    }
+
+    fn load(&mut self, sprite_loader: &mut SpriteLoader) {
+        self.shader = sprite_loader.load_shader(
+            ShaderSource::from_fragment(DEFAULT_FRAGMENT_SHADER).with_software_fragment(flash)
+        );
+    }
+
+    fn draw(&self, sprite_batch: &mut SpriteBatch, renderer: &mut dyn Renderer) -> Result<(), SpriteBatchError> {
+        let flash = |color: [f32; 3]| Some(Effect::new(self.shader, Uniforms::new().with("flash", UniformValue::Vec3(color))));
+
+        sprite_batch.begin(SpriteSortMode::Deferred);
+        for (x, effect) in [None, flash([1f32, 0f32, 0f32]), flash([0f32, 0f32, 1f32]), None].into_iter().enumerate() {
+            sprite_batch.set_effect(effect);
+            sprite_batch.draw(
+                DrawData {
+                    position: Vector2::new(x as f32 * 2f32 - 4f32, -1f32),
+                    scale: Vector2::new(2f32, 2f32),
+                    color: Color::new(0f32, 1f32, 0f32, 1f32),
+                    ..Default::default()
+                }
+            );
+        }
+        sprite_batch.end(renderer)
+    }
+}
+
+#[test]
+fn effects_and_their_uniforms_can_change_between_draws() {
+    let mut application = HeadlessApplication::<Effects>::new(8, 2);
+    let image = application.draw().unwrap();
+
+    let row: Vec<Rgba<u8>> = (0..4).map(|x| *image.get_pixel(x * 2, 0)).collect();
+    assert_eq!(
+        row,
+        vec![Rgba([0, 255, 0, 255]), Rgba([255, 0, 0, 255]), Rgba([0, 0, 255, 255]), Rgba([0, 255, 0, 255])]
+    );
+}
+
+struct BlendModes;
+
+impl ApplicationContext for BlendModes {
+    fn new() -> Self {
+        Self
+    }
+
+    fn draw(&self, sprite_batch: &mut SpriteBatch, renderer: &mut dyn Renderer) -> Result<(), SpriteBatchError> {
+        sprite_batch.begin(SpriteSortMode::Deferred);
+        sprite_batch.clear_color(Color::new(0.5, 0.25, 0f32, 1f32));
+        for (x, blend_mode) in [
+            BlendMode::Alpha,
+            BlendMode::Additive,
+            BlendMode::Multiply,
+            BlendMode::Screen,
+            BlendMode::Opaque
+        ].into_iter().enumerate() {
+            sprite_batch.draw(
+                DrawData {
+                    position: Vector2::new(x as f32 - 2.5, -0.5),
+                    color: Color::new(0f32, 0.5, 1f32, 0.5),
+                    blend_mode: Some(blend_mode),
+                    ..Default::default()
+                }
+            );
+        }
+        sprite_batch.end(renderer)
+    }
+}
+
+#[test]
+fn blend_modes_can_change_between_draws() {
+    let mut application = HeadlessApplication::<BlendModes>::new(5, 1);
+    let image = application.draw().unwrap();
+
+    let expected = [[64, 96, 128, 191], [128, 128, 128, 255], [64, 64, 0, 255], [128, 159, 255, 255], [0, 128, 255, 128]];
+    assert_pixels(image, &expected);
+}
+
+fn assert_pixels(image: &RgbaImage, expected: &[[u8; 4]]) {
+    for (x, expected) in expected.iter().enumerate() {
+        let actual = image.get_pixel(x as u32, 0).0;
+        let matches = actual.iter().zip(expected).all(|(actual, expected)| actual.abs_diff(*expected) <= 1);
+        assert!(matches, "pixel {x} is {actual:?}, expected {expected:?}");
+    }
+}
+
+struct PremultipliedBlendModes;
+
+impl ApplicationContext for PremultipliedBlendModes {
+    fn new() -> Self {
+        Self
+    }
+
+    fn draw(&self, sprite_batch: &mut SpriteBatch, renderer: &mut dyn Renderer) -> Result<(), SpriteBatchError> {
+        sprite_batch.begin(SpriteSortMode::Deferred);
+        sprite_batch.clear_color(Color::new(0.5, 0.25, 0f32, 1f32));
+        let colors = [Color::new(0f32, 0.25, 0.5, 0.5), Color::new(0f32, 0f32, 0f32, 0f32)];
+        for (x, blend_mode) in [BlendMode::PremultipliedAlpha, BlendMode::Multiply, BlendMode::Screen].into_iter().enumerate() {
+            for (y, color) in colors.into_iter().enumerate() {
+                sprite_batch.draw(
+                    DrawData {
+                        position: Vector2::new((x * 2 + y) as f32 - 3f32, -0.5),
+                        color,
+                        blend_mode: Some(blend_mode),
+                        ..Default::default()
+                    }
+                );
+            }
+        }
+        sprite_batch.end(renderer)
+    }
+}
+
+#[test]
+fn premultiplied_blend_modes_weight_the_sprite_by_its_alpha() {
+    let mut application = HeadlessApplication::<PremultipliedBlendModes>::new(6, 1);
+    let image = application.draw().unwrap();
+
+    // Every fully transparent sprite leaves the destination as it was.
+    let destination = [128, 64, 0, 255];
+    let expected = [[64, 96, 128, 255], destination, [64, 48, 0, 255], destination, [128, 112, 128, 255], destination];
+    assert_pixels(image, &expected);
+}
+
+struct Clips;
+
+impl ApplicationContext for Clips {
+    fn new() -> Self {
+        Self
+    }
+
+    fn draw(&self, sprite_batch: &mut SpriteBatch, renderer: &mut dyn Renderer) -> Result<(), SpriteBatchError> {
+        sprite_batch.begin(SpriteSortMode::Deferred);
+        sprite_batch.clear_color(Color::new(0f32, 0f32, 0f32, 1f32));
+        sprite_batch.push_clip(Rectangle::new(-4f32, -4f32, 6f32, 6f32));
+        sprite_batch.push_clip(Rectangle::new(-2f32, -2f32, 6f32, 6f32));
+        sprite_batch.draw(DrawData { position: Vector2::new(-4f32, -4f32), scale: Vector2::ONE * 8f32, ..Default::default() });
+        sprite_batch.pop_clip();
+        sprite_batch.pop_clip();
+        sprite_batch.draw(DrawData { position: Vector2::new(3f32, 3f32), color: Color::new(1f32, 0f32, 0f32, 1f32), ..Default::default() });
+        sprite_batch.end(renderer)
+    }
+}
+
+#[test]
+fn nested_clips_cut_off_sprites_at_their_intersection() {
+    let mut application = HeadlessApplication::<Clips>::new(8, 8);
+    let image = application.draw().unwrap();
+
+    let (white, black) = (Rgba([255, 255, 255, 255]), Rgba([0, 0, 0, 255]));
+    assert_eq!(*image.get_pixel(2, 2), white);
+    assert_eq!(*image.get_pixel(5, 5), white);
+    assert_eq!(*image.get_pixel(1, 2), black);
+    assert_eq!(*image.get_pixel(6, 2), black);
+    assert_eq!(*image.get_pixel(2, 1), black);
+    assert_eq!(*image.get_pixel(5, 6), black);
+    assert_eq!(*image.get_pixel(7, 0), Rgba([255, 0, 0, 255]));
+}
+
+struct RotatedClip;
+
+impl ApplicationContext for RotatedClip {
+    fn new() -> Self {
+        Self
+    }
+
+    fn draw(&self, sprite_batch: &mut SpriteBatch, renderer: &mut dyn Renderer) -> Result<(), SpriteBatchError> {
+        sprite_batch.camera.rotation = FRAC_PI_4;
+        sprite_batch.begin(SpriteSortMode::Deferred);
+        sprite_batch.clear_color(Color::new(0f32, 0f32, 0f32, 1f32));
+        sprite_batch.push_clip(Rectangle::new(-4f32, -4f32, 8f32, 8f32));
+        sprite_batch.draw(DrawData { position: Vector2::new(-16f32, -16f32), scale: Vector2::ONE * 32f32, ..Default::default() });
+        sprite_batch.pop_clip();
+        sprite_batch.end(renderer)
+    }
+}
+
+#[test]
+fn rotated_clips_cut_off_sprites_at_their_bounding_box() {
+    let mut application = HeadlessApplication::<RotatedClip>::new(16, 16);
+    let image = application.draw().unwrap();
+
+    // On screen, the clip is a diamond reaching 5.66 pixels from the centre, inside a bounding box from 2 to 14.
+    // The corners of the bounding box, such as (2, 2), are outside of the diamond but still drawn.
+    let (white, black) = (Rgba([255, 255, 255, 255]), Rgba([0, 0, 0, 255]));
+    assert_eq!(*image.get_pixel(8, 8), white);
+    assert_eq!(*image.get_pixel(8, 2), white);
+    assert_eq!(*image.get_pixel(2, 2), white);
+    assert_eq!(*image.get_pixel(13, 13), white);
+    assert_eq!(*image.get_pixel(1, 8), black);
+    assert_eq!(*image.get_pixel(14, 8), black);
+    assert_eq!(*image.get_pixel(8, 1), black);
+    assert_eq!(*image.get_pixel(8, 14), black);
+}
+
+struct Masks {
+    half: Sprite
+}
+
+impl ApplicationContext for Masks {
+    fn new() -> Self {
+        Self { half: Sprite::default() }
+    }
+
+    fn load(&mut self, sprite_loader: &mut SpriteLoader) {
+        self.half = sprite_loader.load_sprite(RgbaImage::from_fn(2, 1, |x, _| Rgba([255, 255, 255, if x == 0 { 255 } else { 0 }])));
+    }
+
+    fn draw(&self, sprite_batch: &mut SpriteBatch, renderer: &mut dyn Renderer) -> Result<(), SpriteBatchError> {
+        let cover = |color| DrawData {
+            position: Vector2::new(-4f32, -4f32),
+            scale: Vector2::ONE * 8f32,
+            color,
+            depth: 1f32,
+            ..Default::default()
+        };
+
+        sprite_batch.sampler_behaviour.magnify_filter = MagnifySamplerFilter::Nearest;
+
+        // Back to front would draw the deeper content first, if sorting could move sprites past a mask change.
+        sprite_batch.begin(SpriteSortMode::BackToFront);
+        sprite_batch.set_mask(Some(MaskMode::Write { alpha_cutoff: 0.5 }));
+        sprite_batch.draw(
+            DrawData { sprite: self.half, position: Vector2::new(-4f32, -4f32), scale: Vector2::new(4f32, 8f32), ..Default::default() }
+        );
+        sprite_batch.set_mask(Some(MaskMode::Inside));
+        sprite_batch.draw(cover(Color::new(1f32, 0f32, 0f32, 1f32)));
+        sprite_batch.end(renderer)?;
+
+        sprite_batch.begin(SpriteSortMode::Deferred);
+        sprite_batch.set_mask(Some(MaskMode::Outside));
+        sprite_batch.draw(cover(Color::new(0f32, 0f32, 1f32, 1f32)));
+        sprite_batch.set_mask(None);
+        sprite_batch.end(renderer)
+    }
+}
+
+#[test]
+fn masks_limit_sprites_to_inside_or_outside_of_their_opaque_pixels() {
+    let mut application = HeadlessApplication::<Masks>::new(8, 8);
+    let image = application.draw().unwrap();
+
+    for x in 0..8 {
+        let expected = if x < 4 { Rgba([255, 0, 0, 255]) } else { Rgba([0, 0, 255, 255]) };
+        assert_eq!(*image.get_pixel(x, 3), expected, "pixel ({x}, 3)");
+    }
+}
@@ -0,0 +1,146 @@
+use sprite_batching::{
+    Color,
+    DrawCall,
+    DrawData,
+    Mesh,
+    RenderTarget,
+    Renderer,
+    Sprite,
+    SpriteBatch,
+    SpriteBatchError,
+    SpriteLoader,
+    SpriteSortMode,
+    image::RgbaImage
+};
+
+/// Records the sprites of every draw call, each identified by ten times the red channel of its colour.
+#[derive(Default)]
+struct RecordingRenderer {
+    draw_calls: Vec<Vec<u32>>
+}
+
+impl Renderer for RecordingRenderer {
+    fn dimensions(&self) -> (u32, u32) {
+        (64, 64)
+    }
+
+    fn texture_dimensions(&self) -> (u32, u32) {
+        (16, 16)
+    }
+
+    fn draw(&mut self, mesh: &Mesh, _draw_call: &DrawCall) -> Result<(), SpriteBatchError> {
+        let sprites = mesh.vertices.chunks(Mesh::VERTICES_PER_SPRITE)
+            .map(|vertices| (vertices[0].color[0] * 10f32).round() as u32)
+            .collect();
+        self.draw_calls.push(sprites);
+        Ok(())
+    }
+
+    fn clear_render_target(&mut self, _target: RenderTarget, _color: Color) -> Result<(), SpriteBatchError> {
+        Ok(())
+    }
+
+    fn write_render_target(&mut self, _target: RenderTarget, _position: (u32, u32), _image: &RgbaImage) -> Result<(), SpriteBatchError> {
+        Ok(())
+    }
+
+    fn clear_mask(&mut self, _target: Option<RenderTarget>) -> Result<(), SpriteBatchError> {
+        Ok(())
+    }
+}
+
+fn tagged(id: u32, sprite: Sprite, depth: f32) -> DrawData {
+    DrawData { sprite, depth, color: Color::new(id as f32 / 10f32, 0f32, 0f32, 1f32), ..Default::default() }
+}
+
+/// Draws `draws` in a single segment sorted with `sort_mode`, returning the sprites of every draw call.
+fn draw_sorted(sort_mode: SpriteSortMode, draws: Vec<DrawData>) -> Vec<Vec<u32>> {
+    let mut renderer = RecordingRenderer::default();
+    let mut sprite_batch = SpriteBatch::new((64, 64));
+    sprite_batch.begin(sort_mode);
+    for draw_data in draws {
+        sprite_batch.draw(draw_data);
+    }
+    sprite_batch.end(&mut renderer).unwrap();
+    renderer.draw_calls
+}
+
+#[test]
+fn back_to_front_draws_the_greatest_depth_first() {
+    let sprite = Sprite::default();
+    let draw_calls = draw_sorted(
+        SpriteSortMode::BackToFront,
+        vec![tagged(1, sprite, 0.5), tagged(2, sprite, 0.9), tagged(3, sprite, 0.1)]
+    );
+    assert_eq!(draw_calls, vec![vec![2, 1, 3]]);
+}
+
+#[test]
+fn front_to_back_draws_the_smallest_depth_first() {
+    let sprite = Sprite::default();
+    let draw_calls = draw_sorted(
+        SpriteSortMode::FrontToBack,
+        vec![tagged(1, sprite, 0.5), tagged(2, sprite, 0.9), tagged(3, sprite, 0.1)]
+    );
+    assert_eq!(draw_calls, vec![vec![3, 1, 2]]);
+}
+
+#[test]
+fn texture_groups_sprites_by_layer() {
+    let mut sprite_loader = SpriteLoader::new();
+    let first = sprite_loader.load_sprite(RgbaImage::new(4, 4));
+    let second = sprite_loader.load_sprite(RgbaImage::new(4, 4));
+    assert!(first.index() < second.index());
+
+    let draw_calls = draw_sorted(
+        SpriteSortMode::Texture,
+        vec![tagged(1, second, 0f32), tagged(2, first, 0f32), tagged(3, second, 0f32), tagged(4, first, 0f32)]
+    );
+    assert_eq!(draw_calls, vec![vec![2, 4, 1, 3]]);
+}
+
+#[test]
+fn sprites_with_equal_keys_keep_their_order() {
+    let sprite = Sprite::default();
+    let draws = || (1..=5).map(|id| tagged(id, sprite, 0.5)).collect::<Vec<_>>();
+    for sort_mode in [SpriteSortMode::BackToFront, SpriteSortMode::FrontToBack, SpriteSortMode::Texture] {
+        assert_eq!(draw_sorted(sort_mode, draws()), vec![vec![1, 2, 3, 4, 5]], "{:?}", sort_mode);
+    }
+}
+
+#[test]
+fn immediate_submits_every_sprite_with_its_own_draw_call() {
+    let sprite = Sprite::default();
+    let draw_calls = draw_sorted(
+        SpriteSortMode::Immediate,
+        vec![tagged(1, sprite, 0.5), tagged(2, sprite, 0.9), tagged(3, sprite, 0.1)]
+    );
+    assert_eq!(draw_calls, vec![vec![1], vec![2], vec![3]]);
+}
+
+#[test]
+fn draw_immediate_submits_before_the_segment_ends() {
+    let sprite = Sprite::default();
+    let mut renderer = RecordingRenderer::default();
+    let mut sprite_batch = SpriteBatch::new((64, 64));
+    sprite_batch.begin(SpriteSortMode::Immediate);
+
+    sprite_batch.draw_immediate(&mut renderer, tagged(1, sprite, 0f32)).unwrap();
+    assert_eq!(renderer.draw_calls, vec![vec![1]]);
+
+    sprite_batch.draw(tagged(2, sprite, 0f32));
+    sprite_batch.draw_immediate(&mut renderer, tagged(3, sprite, 0f32)).unwrap();
+    assert_eq!(renderer.draw_calls, vec![vec![1], vec![2], vec![3]]);
+
+    sprite_batch.end(&mut renderer).unwrap();
+    assert_eq!(renderer.draw_calls.len(), 3);
+}
+
+#[test]
+#[should_panic]
+fn draw_immediate_requires_an_immediate_segment() {
+    let mut renderer = RecordingRenderer::default();
+    let mut sprite_batch = SpriteBatch::new((64, 64));
+    sprite_batch.begin(SpriteSortMode::Deferred);
+    let _ = sprite_batch.draw_immediate(&mut renderer, DrawData::default());
+}
@@ -0,0 +1,90 @@
+use std::fs;
+use sprite_batching::{
+    Grid,
+    SpriteError,
+    SpriteLoader,
+    SpriteSheet,
+    image::{Rgba, RgbaImage},
+    math::{Rectangle, Vector2}
+};
+
+#[test]
+fn cells_are_numbered_from_the_top_left() {
+    let mut sprite_loader = SpriteLoader::new();
+    let sprite = sprite_loader.load_sprite(RgbaImage::new(32, 48));
+    let sheet = sprite.slice(&Grid::new((16, 16)));
+
+    assert_eq!((sheet.columns(), sheet.rows(), sheet.len()), (2, 3, 6));
+    assert_eq!(sheet.get(0).unwrap().source, Rectangle::new(0f32, 32f32, 16f32, 16f32));
+    assert_eq!(sheet.get(1).unwrap().source, Rectangle::new(16f32, 32f32, 16f32, 16f32));
+    assert_eq!(sheet.cell(0, 2).unwrap().source, Rectangle::new(0f32, 0f32, 16f32, 16f32));
+    assert_eq!(sheet.cell(0, 2), sheet.get(4));
+    assert!(sheet.cell(2, 0).is_none());
+    assert!(sheet.get(6).is_none());
+}
+
+#[test]
+fn margin_and_spacing_are_skipped() {
+    let mut sprite_loader = SpriteLoader::new();
+    // Three columns and two rows with room to spare below them.
+    let sprite = sprite_loader.load_sprite(RgbaImage::new(30, 16));
+
+    let sheet = SpriteSheet::new(sprite, &Grid { cell_dimensions: (8, 4), margin: 2, spacing: 1, ..Default::default() });
+    assert_eq!((sheet.columns(), sheet.rows()), (3, 2));
+    let sources: Vec<_> = sheet.regions().iter().map(|region| region.source).collect();
+    assert_eq!(
+        sources,
+        vec![
+            Rectangle::new(2f32, 10f32, 8f32, 4f32),
+            Rectangle::new(11f32, 10f32, 8f32, 4f32),
+            Rectangle::new(20f32, 10f32, 8f32, 4f32),
+            Rectangle::new(2f32, 5f32, 8f32, 4f32),
+            Rectangle::new(11f32, 5f32, 8f32, 4f32),
+            Rectangle::new(20f32, 5f32, 8f32, 4f32)
+        ]
+    );
+
+    let sheet = SpriteSheet::new(sprite, &Grid { cell_dimensions: (8, 4), margin: 2, spacing: 1, columns: Some(2), rows: Some(1) });
+    assert_eq!(sheet.len(), 2);
+}
+
+#[test]
+#[should_panic]
+fn grids_larger_than_the_sprite_are_rejected() {
+    let mut sprite_loader = SpriteLoader::new();
+    let sprite = sprite_loader.load_sprite(RgbaImage::new(16, 16));
+    sprite.slice(&Grid { cell_dimensions: (8, 8), columns: Some(3), ..Default::default() });
+}
+
+#[test]
+fn invalid_grids_are_reported_as_errors() {
+    let mut sprite_loader = SpriteLoader::new();
+    let sprite = sprite_loader.load_sprite(RgbaImage::new(16, 16));
+    assert!(matches!(SpriteSheet::try_new(sprite, &Grid::new((0, 8))), Err(SpriteError::InvalidGrid { .. })));
+    let result = SpriteSheet::try_new(sprite, &Grid { cell_dimensions: (8, 8), rows: Some(3), ..Default::default() });
+    assert!(matches!(result, Err(SpriteError::InvalidGrid { dimensions: (16, 16), .. })));
+
+    let path = std::env::temp_dir().join(format!("sprite-batching-sheet-{}.png", std::process::id()));
+    RgbaImage::new(16, 8).save(&path).unwrap();
+    let mut sprite_loader = SpriteLoader::new();
+    let sheet = sprite_loader.load_sprite_sheet_file(&path, &Grid { cell_dimensions: (16, 16), rows: Some(1), ..Default::default() });
+    let unloaded = sprite_loader.load_sprite(RgbaImage::new(1, 1));
+    fs::remove_file(&path).unwrap();
+
+    // The sprite of a sheet that failed to load does not stay loaded.
+    assert!(matches!(sheet, Err(SpriteError::InvalidGrid { .. })));
+    assert_eq!(unloaded.index(), 0);
+}
+
+#[test]
+fn regions_are_drawn_without_a_separate_source() {
+    let mut sprite_loader = SpriteLoader::new();
+    let sprite = sprite_loader.load_sprite(RgbaImage::from_pixel(16, 48, Rgba([255, 255, 255, 255])));
+    let region = sprite.slice(&Grid::new((16, 16))).get(1).unwrap();
+
+    let draw_data = sprite_batching::DrawData { position: Vector2::new(3f32, 4f32), ..region.draw_data() };
+    assert_eq!(draw_data.sprite, sprite);
+    assert_eq!(draw_data.source, Some(Rectangle::new(0f32, 16f32, 16f32, 16f32)));
+    assert_eq!(region.dimensions(), (16, 16));
+    assert_eq!(sprite.region(Rectangle::new(0f32, 16f32, 16f32, 16f32)), region);
+}
@@ -0,0 +1,111 @@
+use sprite_batching::{
+    BitmapFont,
+    DrawData,
+    SpriteLoader,
+    TextAlign,
+    TextLayout,
+    TextOptions,
+    image::RgbaImage,
+    math::Vector2
+};
+
+/// A monospaced font whose glyphs are 3 pixels wide and advance the pen by 4, with lines 10 pixels apart.
+fn monospace_font() -> BitmapFont {
+    let mut descriptor = String::from("common lineHeight=10 base=8 scaleW=32 scaleH=32 pages=1\npage id=0 file=\"page.png\"\n");
+    for character in ('a'..='z').chain("[,-".chars()).chain(['\u{4e2d}', '\u{6587}']) {
+        descriptor += &format!(
+            "char id={} x=0 y=0 width=3 height=4 xoffset=0 yoffset=4 xadvance=4 page=0\n",
+            character as u32
+        );
+    }
+    descriptor += "char id=32 x=0 y=0 width=0 height=0 xoffset=0 yoffset=0 xadvance=4 page=0\n";
+
+    let mut sprite_loader = SpriteLoader::new();
+    BitmapFont::from_bytes(descriptor.as_bytes(), |_| Ok(sprite_loader.load_sprite(RgbaImage::new(32, 32)))).unwrap()
+}
+
+/// The left edges of the glyphs of every line, from the top.
+fn lines(draw_data: &[DrawData]) -> Vec<Vec<f32>> {
+    let mut lines: Vec<(f32, Vec<f32>)> = Vec::new();
+    for draw_data in draw_data {
+        match lines.last_mut() {
+            Some((y, line)) if *y == draw_data.position.y => line.push(draw_data.position.x),
+            _ => lines.push((draw_data.position.y, vec![draw_data.position.x]))
+        }
+    }
+    lines.into_iter().map(|(_, line)| line).collect()
+}
+
+#[test]
+fn text_is_measured_without_trailing_spaces() {
+    let font = monospace_font();
+    let options = TextOptions::default();
+
+    assert_eq!(TextLayout::measure(&font, "abc  ", &options), Vector2::new(12f32, 10f32));
+    assert_eq!(TextLayout::measure(&font, "ab\nabcd", &options), Vector2::new(16f32, 20f32));
+    assert_eq!(TextLayout::measure(&font, "ab\n", &options), Vector2::new(8f32, 20f32));
+    assert_eq!(TextLayout::measure(&font, "ab\nab", &TextOptions { line_spacing: 1.5, scale: 2f32, ..Default::default() }).y, 50f32);
+    assert_eq!(TextLayout::measure(&font, "", &options), Vector2::ZERO);
+}
+
+#[test]
+fn long_lines_wrap_at_word_boundaries() {
+    let font = monospace_font();
+    let options = TextOptions { max_width: Some(40f32), ..Default::default() };
+
+    let layout = TextLayout::new(&font, "abc def ghi jkl", &options);
+    assert_eq!(layout.line_count(), 2);
+    assert_eq!(lines(&layout.draw_data(Vector2::ZERO)), vec![vec![0f32, 4f32, 8f32, 16f32, 20f32, 24f32], vec![0f32, 4f32, 8f32, 16f32, 20f32, 24f32]]);
+
+    // Hyphens allow a break after them, ideographs between any two of them, and words longer than a line are split.
+    assert_eq!(TextLayout::new(&font, "abcd-efgh", &TextOptions { max_width: Some(24f32), ..Default::default() }).line_count(), 2);
+    assert_eq!(TextLayout::new(&font, "\u{4e2d}\u{6587}\u{4e2d}\u{6587}", &TextOptions { max_width: Some(8f32), ..Default::default() }).line_count(), 2);
+    let layout = TextLayout::new(&font, "abcdefghijklmnop", &options);
+    assert_eq!(lines(&layout.draw_data(Vector2::ZERO)).iter().map(Vec::len).collect::<Vec<_>>(), vec![10, 6]);
+}
+
+#[test]
+fn lines_are_aligned_inside_the_width() {
+    let font = monospace_font();
+    let text = "ab cd ef\nab";
+    let line_starts = |align| {
+        let layout = TextLayout::new(&font, text, &TextOptions { max_width: Some(24f32), align, ..Default::default() });
+        lines(&layout.draw_data(Vector2::new(100f32, 0f32)))
+    };
+
+    assert_eq!(line_starts(TextAlign::Left), vec![vec![100f32, 104f32, 112f32, 116f32], vec![100f32, 104f32], vec![100f32, 104f32]]);
+    assert_eq!(line_starts(TextAlign::Right), vec![vec![104f32, 108f32, 116f32, 120f32], vec![116f32, 120f32], vec![116f32, 120f32]]);
+    assert_eq!(line_starts(TextAlign::Center), vec![vec![102f32, 106f32, 114f32, 118f32], vec![108f32, 112f32], vec![108f32, 112f32]]);
+
+    // Only wrapped lines are justified, so the last line of every paragraph stays on the left.
+    assert_eq!(line_starts(TextAlign::Justify), vec![vec![100f32, 104f32, 116f32, 120f32], vec![100f32, 104f32], vec![100f32, 104f32]]);
+}
+
+#[test]
+fn markup_changes_the_colour_and_scale() {
+    let font = monospace_font();
+    let options = TextOptions { markup: true, ..Default::default() };
+
+    let draw_data = TextLayout::new(&font, "a[color=#ff0]b[color=#00ff0080]c[/color]d[/color]e", &options).draw_data(Vector2::ZERO);
+    let colors: Vec<_> = draw_data.iter().map(|draw_data| (draw_data.color.red, draw_data.color.green, draw_data.color.blue, draw_data.color.alpha)).collect();
+    assert_eq!(colors, vec![(1f32, 1f32, 1f32, 1f32), (1f32, 1f32, 0f32, 1f32), (0f32, 1f32, 0f32, 128f32 / 255f32), (1f32, 1f32, 0f32, 1f32), (1f32, 1f32, 1f32, 1f32)]);
+
+    // Larger characters push the baseline of their line down.
+    let draw_data = TextLayout::new(&font, "a[scale=2]b[/scale]c", &options).draw_data(Vector2::ZERO);
+    assert_eq!(draw_data.iter().map(|draw_data| draw_data.scale.x).collect::<Vec<_>>(), vec![1f32, 2f32, 1f32]);
+    assert_eq!(draw_data.iter().map(|draw_data| draw_data.position).collect::<Vec<_>>(), vec![
+        Vector2::new(0f32, -16f32),
+        Vector2::new(4f32, -16f32),
+        Vector2::new(12f32, -16f32)
+    ]);
+
+    // Escaped brackets, unknown tags and unmatched closing tags are drawn as text.
+    assert_eq!(TextLayout::new(&font, "[[a] [b=1] [/color]", &options).draw_data(Vector2::ZERO).len(), 10);
+    assert_eq!(TextLayout::new(&font, "[color=#ff0]", &TextOptions::default()).draw_data(Vector2::ZERO).len(), 8);
+
+    // So are scales that are not positive and finite.
+    for value in ["nan", "inf", "-1", "0", "1e39"] {
+        let draw_data = TextLayout::new(&font, &format!("[scale={value}]a"), &options).draw_data(Vector2::ZERO);
+        assert!(draw_data.len() > 6 && draw_data.iter().all(|draw_data| draw_data.scale.x == 1f32), "[scale={value}]");
+    }
+}
@@ -0,0 +1,177 @@
+use std::fs;
+use sprite_batching::{
+    AtlasError,
+    DrawData,
+    Mesh,
+    SpriteLoader,
+    TagDirection,
+    TextureAtlas,
+    image::RgbaImage,
+    math::{Matrix4x4, Rectangle, Vector2}
+};
+
+const TEXTURE_PACKER_HASH: &str = r#"{"frames": {
+    "hero.png": {
+        "frame": {"x":2,"y":4,"w":10,"h":6},
+        "rotated": false,
+        "trimmed": true,
+        "spriteSourceSize": {"x":3,"y":1,"w":10,"h":6},
+        "sourceSize": {"w":16,"h":8},
+        "pivot": {"x":0.5,"y":1}
+    },
+    "sword.png": {
+        "frame": {"x":20,"y":0,"w":4,"h":12},
+        "rotated": true,
+        "trimmed": false,
+        "spriteSourceSize": {"x":0,"y":0,"w":4,"h":12},
+        "sourceSize": {"w":4,"h":12}
+    }
+},
+"meta": {"app": "https://www.codeandweb.com/texturepacker", "image": "sheet.png", "format": "RGBA8888", "size": {"w":64,"h":32}, "scale": "1"}
+}"#;
+
+const TEXTURE_PACKER_ARRAY: &str = r#"{"frames": [
+    {
+        "filename": "hero.png",
+        "frame": {"x":2,"y":4,"w":10,"h":6},
+        "rotated": false,
+        "trimmed": true,
+        "spriteSourceSize": {"x":3,"y":1,"w":10,"h":6},
+        "sourceSize": {"w":16,"h":8},
+        "pivot": {"x":0.5,"y":1}
+    },
+    {
+        "filename": "sword.png",
+        "frame": {"x":20,"y":0,"w":4,"h":12},
+        "rotated": true,
+        "trimmed": false,
+        "spriteSourceSize": {"x":0,"y":0,"w":4,"h":12},
+        "sourceSize": {"w":4,"h":12}
+    }
+],
+"meta": {"image": "sheet.png", "size": {"w":64,"h":32}}
+}"#;
+
+const ASEPRITE: &str = r##"{ "frames": {
+   "slime 0.aseprite": { "frame": { "x": 0, "y": 0, "w": 16, "h": 16 }, "rotated": false, "trimmed": false, "spriteSourceSize": { "x": 0, "y": 0, "w": 16, "h": 16 }, "sourceSize": { "w": 16, "h": 16 }, "duration": 100 },
+   "slime 1.aseprite": { "frame": { "x": 16, "y": 0, "w": 16, "h": 16 }, "rotated": false, "trimmed": false, "spriteSourceSize": { "x": 0, "y": 0, "w": 16, "h": 16 }, "sourceSize": { "w": 16, "h": 16 }, "duration": 150 },
+   "slime 2.aseprite": { "frame": { "x": 32, "y": 0, "w": 16, "h": 16 }, "rotated": false, "trimmed": false, "spriteSourceSize": { "x": 0, "y": 0, "w": 16, "h": 16 }, "sourceSize": { "w": 16, "h": 16 }, "duration": 250 }
+ },
+ "meta": {
+  "app": "https://www.aseprite.org/",
+  "image": "slime.png",
+  "format": "RGBA8888",
+  "size": { "w": 48, "h": 16 },
+  "scale": "1",
+  "frameTags": [
+   { "name": "idle", "from": 0, "to": 1, "direction": "forward", "color": "#000000ff" },
+   { "name": "hop", "from": 1, "to": 2, "direction": "pingpong", "repeat": "2", "color": "#000000ff" }
+  ]
+ }
+}"##;
+
+fn load(json: &str, image_dimensions: (u32, u32)) -> TextureAtlas {
+    let mut sprite_loader = SpriteLoader::new();
+    TextureAtlas::from_json(json.as_bytes(), |_| Ok(sprite_loader.load_sprite(RgbaImage::new(image_dimensions.0, image_dimensions.1)))).unwrap()
+}
+
+#[test]
+fn texture_packer_frames_are_read_from_hashes_and_arrays() {
+    let atlas = load(TEXTURE_PACKER_HASH, (64, 32));
+    assert_eq!(atlas, load(TEXTURE_PACKER_ARRAY, (64, 32)));
+    assert_eq!(atlas.frames().iter().map(|frame| frame.name.as_str()).collect::<Vec<_>>(), vec!["hero.png", "sword.png"]);
+
+    let hero = atlas.frame("hero.png").unwrap();
+    assert_eq!(hero.region.source, Rectangle::new(2f32, 22f32, 10f32, 6f32));
+    assert!(hero.trimmed());
+    assert_eq!(hero.offset, Vector2::new(3f32, 1f32));
+    assert_eq!(hero.source_dimensions, (16, 8));
+    assert_eq!(hero.pivot, Vector2::new(0.5, 0f32));
+    assert_eq!(hero.duration, None);
+
+    // The pivot of the untrimmed sprite is placed at the position.
+    let draw_data = hero.draw_data();
+    assert_eq!((draw_data.source, draw_data.origin, draw_data.rotation), (Some(hero.region.source), Vector2::new(5f32, -1f32), 0f32));
+
+    let sword = atlas.frame("sword.png").unwrap();
+    assert!(sword.rotated && !sword.trimmed());
+    assert_eq!(sword.region.source, Rectangle::new(20f32, 28f32, 12f32, 4f32));
+    assert!(atlas.tags().is_empty());
+}
+
+#[test]
+fn rotated_frames_are_drawn_upright() {
+    let atlas = load(TEXTURE_PACKER_HASH, (64, 32));
+    let sword = atlas.frame("sword.png").unwrap();
+    let mesh = Mesh::from_draw_data(
+        &[DrawData { position: Vector2::new(100f32, 100f32), ..sword.draw_data() }],
+        Matrix4x4::new_scaling(1f32, 1f32, 1f32),
+        (64, 32)
+    );
+
+    // The upright sprite is 4 by 12 pixels around its centre, and its top-left corner is the top-right corner of the packed pixels.
+    for vertex in &mesh.vertices {
+        assert!((vertex.position[0] - 100f32).abs() - 2f32 < 1e-4 && (vertex.position[1] - 100f32).abs() - 6f32 < 1e-4);
+    }
+    let top_left = mesh.vertices.iter().find(|vertex| vertex.position[0] < 100f32 && vertex.position[1] > 100f32).unwrap();
+    assert_eq!(top_left.uv, [0.5, 1f32]);
+    assert!((top_left.position[0] - 98f32).abs() < 1e-4 && (top_left.position[1] - 106f32).abs() < 1e-4);
+}
+
+#[test]
+fn aseprite_tags_and_durations_are_kept() {
+    let atlas = load(ASEPRITE, (48, 16));
+
+    let durations: Vec<_> = atlas.frames().iter().map(|frame| frame.duration.unwrap()).collect();
+    assert_eq!(durations, vec![0.1, 0.15, 0.25]);
+    assert_eq!(atlas.frames()[2].region.source, Rectangle::new(32f32, 0f32, 16f32, 16f32));
+
+    let idle = atlas.tag("idle").unwrap();
+    assert_eq!((idle.from, idle.to, idle.direction, idle.repeat), (0, 1, TagDirection::Forward, None));
+    let hop = atlas.tag("hop").unwrap();
+    assert_eq!((hop.direction, hop.repeat), (TagDirection::PingPong, Some(2)));
+    assert_eq!(atlas.tag_frames("hop").unwrap().iter().map(|frame| frame.name.as_str()).collect::<Vec<_>>(), vec!["slime 1.aseprite", "slime 2.aseprite"]);
+    assert!(atlas.tag_frames("run").is_none());
+}
+
+#[test]
+fn atlases_load_their_image_next_to_the_metadata() {
+    let directory = std::env::temp_dir().join(format!("sprite-batching-atlas-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("slime.json"), ASEPRITE).unwrap();
+    RgbaImage::new(48, 16).save(directory.join("slime.png")).unwrap();
+
+    let mut sprite_loader = SpriteLoader::new();
+    let atlas = TextureAtlas::load_file(directory.join("slime.json"), &mut sprite_loader);
+    let missing = TextureAtlas::load_file(directory.join("missing.json"), &mut sprite_loader);
+    fs::remove_dir_all(&directory).unwrap();
+
+    assert_eq!(atlas.unwrap().sprite().dimensions(), (48, 16));
+    assert!(matches!(missing, Err(AtlasError::Io(_))));
+    assert!(matches!(TextureAtlas::from_json(b"{", |_| unreachable!()), Err(AtlasError::Json(_))));
+}
+
+#[test]
+fn malformed_metadata_does_not_load_the_image() {
+    let documents: [&[u8]; 3] = [
+        br#"{"frames": 3, "meta": {"image": "sheet.png"}}"#,
+        br#"{"frames": {"a": {"frame": {"x": 0, "y": 0, "w": 8}}}, "meta": {"image": "sheet.png"}}"#,
+        br#"{"frames": [], "meta": {"image": "sheet.png", "frameTags": [{"name": "idle", "from": -1, "to": 0}]}}"#
+    ];
+    for document in documents {
+        let result = TextureAtlas::from_json(document, |_| panic!("the image of malformed metadata was loaded"));
+        assert!(matches!(result, Err(AtlasError::Json(_))));
+    }
+
+    let directory = std::env::temp_dir().join(format!("sprite-batching-malformed-atlas-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("sheet.json"), documents[1]).unwrap();
+    RgbaImage::new(8, 8).save(directory.join("sheet.png")).unwrap();
+    let mut sprite_loader = SpriteLoader::new();
+    let atlas = TextureAtlas::load_file(directory.join("sheet.json"), &mut sprite_loader);
+    let next = sprite_loader.load_sprite(RgbaImage::new(1, 1));
+    fs::remove_dir_all(&directory).unwrap();
+
+    assert!(matches!(atlas, Err(AtlasError::Json(_))));
+    assert_eq!(next.index(), 0);
+}